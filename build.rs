@@ -1,11 +1,22 @@
 const COMMANDS: &[&str] = &[
     "spawn",
+    "exec",
     "kill",
     "kill_all",
     "restart",
     "list_processes",
+    "list_orphans",
     "get_status",
+    "wait",
+    "wait_for_output",
+    "subscribe",
+    "unsubscribe",
     "write_stdin",
+    "write_stdin_bytes",
+    "close_stdin",
+    "write_ipc",
+    "signal",
+    "resize",
     "detect_runtimes",
     "set_runtime_path",
     "get_runtime_paths",
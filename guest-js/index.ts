@@ -1,5 +1,5 @@
 import { type IoInterface, type IoMessage } from "kkrpc/browser";
-import { invoke } from "@tauri-apps/api/core";
+import { Channel, invoke } from "@tauri-apps/api/core";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 
 // ── Types ──
@@ -12,22 +12,453 @@ export interface SpawnConfig {
   args?: string[];
   cwd?: string;
   env?: Record<string, string>;
+  shutdown?: ShutdownConfig;
+  restartPolicy?: RestartPolicy;
+  /** How stdout lines are decoded. Defaults to "lossy". */
+  stdoutEncoding?: OutputEncoding;
+  /** How stderr lines are decoded. Defaults to "lossy". */
+  stderrEncoding?: OutputEncoding;
+  /**
+   * How stdout is split into messages and stdin writes are framed. Defaults to "newline".
+   * With `ipc`, applies to the IPC pipes instead.
+   */
+  framing?: Framing;
+  /**
+   * Open a dedicated RPC pipe pair on fds 3 (child reads) and 4 (child writes), announced
+   * to the child as `TAURI_JS_IPC_FDS=3,4`. Unix only.
+   */
+  ipc?: boolean;
+  /**
+   * Listen on a private Unix socket whose path is passed to the child as `TAURI_JS_SOCKET`.
+   * Every connection is a separate message stream; the socket stays open until the process
+   * is stopped or replaced. Unix only.
+   */
+  socket?: boolean;
+  /**
+   * Emit all of the process's events to every webview. Defaults to true;
+   * set to false to deliver them only to `subscribe` callbacks.
+   */
+  broadcast?: boolean;
+  /** Size of the buffer of recent output that `subscribe` can replay */
+  replay?: ReplayConfig;
+  /** Coalesce stdout and stderr lines into `js-process-output-batch` events */
+  batch?: BatchConfig;
+  /** Cap the stdout and stderr lines forwarded per second. IPC messages are never limited. */
+  rateLimit?: RateLimitConfig;
+  /** Limit the writes waiting for stdin, the IPC pipe or a socket connection */
+  writeQueue?: WriteQueueConfig;
+  /**
+   * Start the process in its own process group, so `kill`, `killAll`, `restart` and app exit
+   * stop everything it spawned, not just the process itself. Unix only.
+   */
+  processGroup?: boolean;
+  /**
+   * Have the kernel kill the process if the app dies without shutting it down, e.g. when it
+   * crashes or is killed. Linux only. The kernel actually watches the thread that spawned the
+   * process; spawns from the frontend run on threads that live as long as the app.
+   */
+  killOnParentDeath?: boolean;
+  /**
+   * Runs the process in a pseudo-terminal, for interactive runtimes and REPLs. stdout and
+   * stderr arrive together as raw stdout chunks, and stdin takes keystrokes. Unix only.
+   */
+  pty?: boolean;
+  /** Initial terminal size with `pty`. Defaults to 80x24. */
+  ptySize?: PtySize;
+  /**
+   * Tells when the process is ready. Its state stays "starting" until the probe succeeds,
+   * moving it to "ready", or gives up, moving it to "running".
+   */
+  readiness?: ReadinessConfig;
+}
+
+/** Exactly one of `pattern`, `tcpPort`, `httpUrl` and `ping` must be set. */
+export interface ReadinessConfig {
+  /** Ready once a line of stdout contains this */
+  pattern?: string;
+  /** Treat `pattern`, or the ping's `response`, as a regular expression (Rust syntax) */
+  regex?: boolean;
+  /** Ready once a TCP connection to this port on localhost succeeds */
+  tcpPort?: number;
+  /** Ready once a GET of this http:// URL on localhost, 127.0.0.1 or [::1] returns 2xx */
+  httpUrl?: string;
+  /** Ready once the process answers a message on stdin */
+  ping?: PingProbe;
+  /** Gives up on the process becoming ready after this long. Defaults to 30000. */
+  timeoutMs?: number;
+  /**
+   * Time between TCP, HTTP and ping attempts. Defaults to 250. A TCP or HTTP attempt that
+   * takes longer, but at least 1s and at most 5s, fails.
+   */
+  intervalMs?: number;
+  /**
+   * Make `spawn` and `restart` resolve only once the process is ready, and stop it and reject
+   * if it does not become ready
+   */
+  wait?: boolean;
+}
+
+export interface PingProbe {
+  /** Written to stdin as is, like `writeStdin`, every `intervalMs` until answered */
+  message: string;
+  /** Expected in a line of stdout */
+  response: string;
+}
+
+export interface PtySize {
+  cols: number;
+  rows: number;
+}
+
+export interface WriteQueueConfig {
+  /** Writes that can be waiting per pipe or connection. Defaults to 64. */
+  maxPending?: number;
+  /**
+   * What a write does when the queue is full: wait for room, or fail with a "write queue
+   * full" error. Defaults to "wait".
+   */
+  whenFull?: "wait" | "error";
+}
+
+export interface ReplayConfig {
+  /**
+   * Most stdout, stderr and IPC messages kept, counting each line of a batch. Defaults to
+   * 1000; 0 disables the buffer.
+   */
+  maxLines?: number;
+  /** Most bytes of message data kept. Defaults to 1048576. */
+  maxBytes?: number;
+}
+
+export interface BatchConfig {
+  /** How long lines are collected before they are emitted. Defaults to 16. */
+  intervalMs?: number;
+  /** Bytes of line data after which a batch is emitted early. Defaults to 65536. */
+  maxBytes?: number;
+}
+
+export interface RateLimitConfig {
+  /** Most stdout and stderr lines forwarded per second */
+  maxLinesPerSec?: number;
+  /** Most bytes of stdout and stderr forwarded per second */
+  maxBytesPerSec?: number;
+  /** What happens to lines over the limit. Defaults to "drop". */
+  policy?: OverflowPolicy;
+  /** With "sample", one in this many lines over the limit is forwarded. Defaults to 100. */
+  sampleEvery?: number;
+  /**
+   * With "spill", size at which the spill file is rotated, keeping the previous one with a
+   * `.1` suffix. Defaults to 16 MiB.
+   */
+  maxSpillBytes?: number;
+}
+
+/**
+ * - `drop`: lines over the limit are discarded
+ * - `sample`: one in `sampleEvery` lines over the limit is forwarded, the rest discarded
+ * - `spill`: lines over the limit are appended to a file in the app's log directory
+ */
+export type OverflowPolicy = "drop" | "sample" | "spill";
+
+/**
+ * - `newline`: one message per line; stdin data is written as is
+ * - `nul`: messages end with a NUL byte, which is appended to stdin writes
+ * - `length-prefixed`: messages start with a 4-byte big-endian length, which is prepended to
+ *   stdin writes
+ * - `raw`: stdout is emitted in chunks as it arrives; stdin data is written as is
+ */
+export type Framing = "newline" | "nul" | "length-prefixed" | "raw";
+
+/**
+ * - `lossy`: UTF-8, invalid bytes replaced with U+FFFD
+ * - `strict`: UTF-8, invalid lines reported via `js-process-decode-error` instead
+ * - `base64`: raw bytes of each line, base64-encoded
+ */
+export type OutputEncoding = "lossy" | "strict" | "base64";
+
+export interface ShutdownConfig {
+  /**
+   * Signal used to request a graceful stop (ignored on Windows). Defaults to "SIGTERM",
+   * or to no signal once `message` has been written.
+   */
+  signal?: "SIGTERM" | "SIGINT" | "SIGHUP" | "SIGQUIT";
+  /** Message written to stdin before it is closed */
+  message?: string;
+  /** Milliseconds to wait, writing `message` included, before force-killing. Defaults to 5000. */
+  timeoutMs?: number;
+}
+
+export interface RestartPolicy {
+  mode: "never" | "on-failure" | "always";
+  /** Restarts allowed within `windowMs` before giving up. Defaults to 5. */
+  maxRetries?: number;
+  /** Crash-loop detection window. Defaults to 60000. */
+  windowMs?: number;
+  /** First restart delay, doubled per restart within the window. Defaults to 500. */
+  initialBackoffMs?: number;
+  /** Maximum restart delay. Defaults to 30000. */
+  maxBackoffMs?: number;
+}
+
+/** Signals accepted by `signal` */
+export type Signal =
+  | "SIGHUP"
+  | "SIGINT"
+  | "SIGQUIT"
+  | "SIGTERM"
+  | "SIGUSR1"
+  | "SIGUSR2"
+  | "SIGSTOP"
+  | "SIGCONT"
+  | "SIGWINCH";
+
+export type ShutdownOutcome = "graceful" | "killed" | "alreadyExited";
+
+export interface ShutdownResult {
+  name: string;
+  instanceId: number;
+  outcome: ShutdownOutcome;
+  code: number | null;
+  signal: number | null;
+  elapsedMs: number;
+}
+
+export interface ExecOptions {
+  /** Kills the process if it is still running after this long */
+  timeoutMs?: number;
+  /** Written to the process's stdin, which is then closed. Without it stdin is empty. */
+  stdin?: string;
+  /** Most bytes of stdout and of stderr kept; the rest is discarded. Defaults to 1048576. */
+  maxOutputBytes?: number;
+}
+
+export interface ExecResult {
+  code: number | null;
+  /** Terminating signal (Unix only) */
+  signal: number | null;
+  /** UTF-8 with invalid bytes replaced, or base64 with `stdoutEncoding: "base64"` */
+  stdout: string;
+  /** Like `stdout`, according to `stderrEncoding` */
+  stderr: string;
+  stdoutTruncated: boolean;
+  stderrTruncated: boolean;
+  /** Whether the process was killed for running past `timeoutMs` */
+  timedOut: boolean;
+  durationMs: number;
+}
+
+export interface WaitForOutputOptions {
+  /** Stream to watch. Defaults to "stdout". */
+  stream?: StdioStream;
+  /** Rejects once this long has passed, leaving the process running */
+  timeoutMs?: number;
+  /** Instance to watch; defaults to the current one */
+  instanceId?: number;
+}
+
+export interface OutputMatch {
+  name: string;
+  instanceId: number;
+  seq: number;
+  stream: StdioStream;
+  capturedAt: number;
+  line: string;
+  /**
+   * The whole match followed by the regex's capture groups, `null` for groups that did not
+   * take part
+   */
+  captures: (string | null)[];
+  namedCaptures: Record<string, string>;
+}
+
+export type ProcessState =
+  | "starting"
+  | "ready"
+  | "running"
+  | "suspended"
+  | "orphaned"
+  | "stopping"
+  | "exited"
+  | "failed"
+  | "restarting";
+
+/** What happens at startup to processes a previous run of the app left running */
+export type OrphanPolicy = "terminate" | "reattach" | "ignore";
+
+/** A process left running by a previous run of the app, found at startup */
+export interface OrphanInfo {
+  name: string;
+  pid: number;
+  /** Unix timestamp in milliseconds at which the previous run spawned it */
+  startedAt: number;
+  /** Program and arguments it was started with */
+  command: string[];
+  cwd: string | null;
+  /** What was done with it */
+  policy: OrphanPolicy;
+  /** Instance id it was reattached as, with the "reattach" policy */
+  instanceId: number | null;
 }
 
 export interface ProcessInfo {
   name: string;
+  /** Unique per spawned instance; changes on every (re)start */
+  instanceId: number;
   pid: number | null;
+  state: ProcessState;
+  /** True while the process is alive (starting, ready, running, suspended, orphaned or stopping) */
   running: boolean;
+  /** Unix timestamp in milliseconds */
+  startedAt: number;
+  /** Unix timestamp in milliseconds */
+  exitedAt: number | null;
+  exitCode: number | null;
+  /** Terminating signal (Unix only) */
+  exitSignal: number | null;
+  exitReason: ExitReason | null;
+  /** Automatic restarts since the process was last spawned explicitly */
+  restartCount: number;
+  /** Config this instance was spawned with */
+  config: SpawnConfig;
+  /** Output held back by the rate limit */
+  outputStats: OutputStats;
+}
+
+/** Stdout and stderr lines held back by `rateLimit` since the instance started */
+export interface OutputStats {
+  /** Lines discarded by the "drop" and "sample" policies */
+  droppedLines: number;
+  droppedBytes: number;
+  /** Lines written to the spill file instead of being emitted */
+  spilledLines: number;
+  spilledBytes: number;
+  /** File lines are spilled to, once the "spill" policy kicked in */
+  spillPath: string | null;
 }
 
 export interface StdioEventPayload {
   name: string;
+  instanceId: number;
+  /**
+   * Position in the process's output across stdout, stderr and IPC, counting across
+   * restarts, so streams can be merged in order and gaps detected. Assigned when the line is
+   * read, so lines held back by `rateLimit` leave a gap
+   */
+  seq: number;
+  stream: StdioStream;
+  /** Unix timestamp in milliseconds at which the line was read from the process */
+  capturedAt: number;
   data: string;
 }
 
+export interface IpcEventPayload {
+  name: string;
+  instanceId: number;
+  /** Socket connection the message arrived on; null for the IPC pipe */
+  connectionId: number | null;
+  /** Position in the process's output, see `StdioEventPayload.seq` */
+  seq: number;
+  stream: "ipc";
+  /** Unix timestamp in milliseconds at which the message was read from the process */
+  capturedAt: number;
+  data: string;
+}
+
+/** Stdout or stderr lines coalesced by `batch` */
+export interface OutputBatchEventPayload {
+  name: string;
+  instanceId: number;
+  /** Position of the first line in the process's output, see `StdioEventPayload.seq` */
+  seq: number;
+  stream: "stdout" | "stderr";
+  /** Unix timestamp in milliseconds at which the first line was read from the process */
+  capturedAt: number;
+  lines: string[];
+  /**
+   * Position of each line; not consecutive when output of other streams was read in between
+   * or lines were held back by `rateLimit`
+   */
+  seqs: number[];
+}
+
+/** Reported while `rateLimit` is holding back output, at most once per second */
+export interface ThrottleEventPayload {
+  name: string;
+  instanceId: number;
+  policy: OverflowPolicy;
+  /** Totals since the instance started */
+  stats: OutputStats;
+}
+
+/** Event delivered to a `subscribe` callback */
+export type ProcessEvent =
+  | { event: "stdout"; data: StdioEventPayload }
+  | { event: "stderr"; data: StdioEventPayload }
+  | { event: "ipc"; data: IpcEventPayload }
+  | { event: "batch"; data: OutputBatchEventPayload }
+  | { event: "ready"; data: ReadinessEventPayload }
+  | { event: "readinessFailed"; data: ReadinessEventPayload }
+  | { event: "exit"; data: ExitEventPayload }
+  | { event: "decodeError"; data: DecodeErrorEventPayload }
+  | { event: "throttled"; data: ThrottleEventPayload }
+  | { event: "connection"; data: ConnectionEventPayload }
+  | { event: "restart"; data: RestartEventPayload }
+  | { event: "crashLoop"; data: CrashLoopEventPayload };
+
+export interface ConnectionEventPayload {
+  name: string;
+  instanceId: number;
+  connectionId: number;
+  /** false once the connection has closed */
+  connected: boolean;
+}
+
+export type StdioStream = "stdout" | "stderr" | "ipc";
+
+export interface DecodeErrorEventPayload {
+  name: string;
+  instanceId: number;
+  stream: StdioStream;
+  error: string;
+  /** The offending line, decoded lossily; empty for a skipped frame */
+  data: string;
+}
+
+export interface ReadinessEventPayload {
+  name: string;
+  instanceId: number;
+  /** Time between spawn and the probe succeeding or giving up */
+  elapsedMs: number;
+  /** Why the process did not become ready; `null` once it is ready */
+  error: string | null;
+}
+
+export type ExitReason = "exited" | "killed-by-request" | "crashed" | "restart";
+
 export interface ExitEventPayload {
   name: string;
+  instanceId: number;
   code: number | null;
+  /** Terminating signal (Unix only) */
+  signal: number | null;
+  reason: ExitReason;
+  uptimeMs: number;
+}
+
+export interface RestartEventPayload {
+  name: string;
+  instanceId: number;
+  code: number | null;
+  attempt: number;
+  delayMs: number;
+}
+
+export interface CrashLoopEventPayload {
+  name: string;
+  instanceId: number;
+  code: number | null;
+  restarts: number;
+  windowMs: number;
 }
 
 export interface RuntimeInfo {
@@ -46,21 +477,47 @@ export async function spawn(
   return invoke<ProcessInfo>("plugin:js|spawn", { name, config });
 }
 
-export async function kill(name: string): Promise<void> {
-  return invoke<void>("plugin:js|kill", { name });
+/**
+ * Runs a command to completion, e.g. `bun build` or `deno fmt --check`, and returns its
+ * output and exit status. The process is not tracked by name and emits no events.
+ */
+export async function exec(
+  config: SpawnConfig,
+  options?: ExecOptions,
+): Promise<ExecResult> {
+  return invoke<ExecResult>("plugin:js|exec", {
+    config,
+    options: options ?? null,
+  });
+}
+
+export async function kill(
+  name: string,
+  instanceId?: number,
+): Promise<ShutdownResult> {
+  return invoke<ShutdownResult>("plugin:js|kill", {
+    name,
+    instanceId: instanceId ?? null,
+  });
 }
 
-export async function killAll(): Promise<void> {
-  return invoke<void>("plugin:js|kill_all");
+export async function killAll(): Promise<ShutdownResult[]> {
+  return invoke<ShutdownResult[]>("plugin:js|kill_all");
 }
 
+/**
+ * Stops a process and spawns it again with `config`, or with the config it was last spawned
+ * with. An orphan reattached from a previous run can only be restarted with a `config`.
+ */
 export async function restart(
   name: string,
   config?: SpawnConfig,
+  instanceId?: number,
 ): Promise<ProcessInfo> {
   return invoke<ProcessInfo>("plugin:js|restart", {
     name,
     config: config ?? null,
+    instanceId: instanceId ?? null,
   });
 }
 
@@ -68,12 +525,173 @@ export async function listProcesses(): Promise<ProcessInfo[]> {
   return invoke<ProcessInfo[]>("plugin:js|list_processes");
 }
 
-export async function getStatus(name: string): Promise<ProcessInfo> {
-  return invoke<ProcessInfo>("plugin:js|get_status", { name });
+/** Processes left running by previous runs of the app and what was done with them. */
+export async function listOrphans(): Promise<OrphanInfo[]> {
+  return invoke<OrphanInfo[]>("plugin:js|list_orphans");
+}
+
+export async function getStatus(
+  name: string,
+  instanceId?: number,
+): Promise<ProcessInfo> {
+  return invoke<ProcessInfo>("plugin:js|get_status", {
+    name,
+    instanceId: instanceId ?? null,
+  });
+}
+
+/**
+ * Resolves with how an instance of `name` exited, by default the current one, right away if
+ * it already has; the last 8 instances the name was spawned over still count. Rejects once
+ * `timeoutMs` has passed, leaving the process running.
+ */
+export async function wait(
+  name: string,
+  timeoutMs?: number,
+  instanceId?: number,
+): Promise<ExitEventPayload> {
+  return invoke<ExitEventPayload>("plugin:js|wait", {
+    name,
+    timeoutMs: timeoutMs ?? null,
+    instanceId: instanceId ?? null,
+  });
+}
+
+/**
+ * Resolves with the first line of an instance's output, by default stdout of the current
+ * one, that contains `pattern` or matches it if it is a RegExp. Buffered output counts, so
+ * a line printed before the call matches right away. Rejects if the instance exits first.
+ * RegExp flags are not carried over; use inline flags such as `(?i)` instead.
+ */
+export async function waitForOutput(
+  name: string,
+  pattern: string | RegExp,
+  options?: WaitForOutputOptions,
+): Promise<OutputMatch> {
+  const regex = pattern instanceof RegExp;
+  return invoke<OutputMatch>("plugin:js|wait_for_output", {
+    name,
+    pattern: regex ? pattern.source : pattern,
+    options: { ...options, regex },
+  });
+}
+
+/**
+ * Streams the events of every instance of `name`, output and lifecycle alike, to
+ * this webview only. The subscription ends when the returned function is called or the page
+ * goes away.
+ *
+ * @param fromSeq Replay buffered output from this sequence number on before streaming live
+ * events; 0 replays everything still buffered.
+ */
+export async function subscribe(
+  name: string,
+  onEvent: (event: ProcessEvent) => void,
+  fromSeq?: number,
+): Promise<UnlistenFn> {
+  const channel = new Channel<ProcessEvent>();
+  channel.onmessage = onEvent;
+  const subscriptionId = await invoke<number>("plugin:js|subscribe", {
+    name,
+    onEvent: channel,
+    fromSeq: fromSeq ?? null,
+  });
+  return () => {
+    void invoke<void>("plugin:js|unsubscribe", { subscriptionId });
+  };
+}
+
+export async function writeStdin(
+  name: string,
+  data: string,
+  instanceId?: number,
+): Promise<void> {
+  return invoke<void>("plugin:js|write_stdin", {
+    name,
+    data,
+    instanceId: instanceId ?? null,
+  });
 }
 
-export async function writeStdin(name: string, data: string): Promise<void> {
-  return invoke<void>("plugin:js|write_stdin", { name, data });
+/** Writes binary data to a process's stdin, framed like `writeStdin`. */
+export async function writeStdinBytes(
+  name: string,
+  data: Uint8Array,
+  instanceId?: number,
+): Promise<void> {
+  // Sent base64-encoded; build the binary string in chunks to stay within argument limits
+  let binary = "";
+  for (let i = 0; i < data.length; i += 0x8000) {
+    binary += String.fromCharCode(...data.subarray(i, i + 0x8000));
+  }
+  return invoke<void>("plugin:js|write_stdin_bytes", {
+    name,
+    data: btoa(binary),
+    instanceId: instanceId ?? null,
+  });
+}
+
+/**
+ * Closes a process's stdin after the writes already queued, so it sees EOF, while leaving it
+ * running. Rejects for a `pty` process; write `"\x04"` to it instead.
+ */
+export async function closeStdin(
+  name: string,
+  instanceId?: number,
+): Promise<void> {
+  return invoke<void>("plugin:js|close_stdin", {
+    name,
+    instanceId: instanceId ?? null,
+  });
+}
+
+/**
+ * Sends a signal to a running process (Unix only). `SIGSTOP` moves it to the "suspended"
+ * state and `SIGCONT` resumes it.
+ */
+export async function signal(
+  name: string,
+  signal: Signal,
+  instanceId?: number,
+): Promise<void> {
+  return invoke<void>("plugin:js|signal", {
+    name,
+    signal,
+    instanceId: instanceId ?? null,
+  });
+}
+
+/** Resizes the terminal of a process spawned with `pty: true`, which is sent SIGWINCH. */
+export async function resize(
+  name: string,
+  cols: number,
+  rows: number,
+  instanceId?: number,
+): Promise<void> {
+  return invoke<void>("plugin:js|resize", {
+    name,
+    cols,
+    rows,
+    instanceId: instanceId ?? null,
+  });
+}
+
+/**
+ * Sends a message to a process spawned with `ipc: true` or `socket: true`. Socket messages
+ * go to `connectionId`, or to every open connection if it is omitted.
+ */
+export async function writeIpc(
+  name: string,
+  data: string,
+  instanceId?: number,
+  connectionId?: number,
+): Promise<void> {
+  return invoke<void>("plugin:js|write_ipc", {
+    name,
+    data,
+    instanceId: instanceId ?? null,
+    connectionId: connectionId ?? null,
+  });
 }
 
 export async function detectRuntimes(): Promise<RuntimeInfo[]> {
@@ -95,33 +713,135 @@ export async function getRuntimePaths(): Promise<Record<string, string>> {
 
 export function onStdout(
   name: string,
-  callback: (data: string) => void,
+  callback: (data: string, payload: StdioEventPayload) => void,
 ): Promise<UnlistenFn> {
   return listen<StdioEventPayload>("js-process-stdout", (event) => {
     if (event.payload.name === name) {
-      callback(event.payload.data);
+      callback(event.payload.data, event.payload);
     }
   });
 }
 
 export function onStderr(
   name: string,
-  callback: (data: string) => void,
+  callback: (data: string, payload: StdioEventPayload) => void,
 ): Promise<UnlistenFn> {
   return listen<StdioEventPayload>("js-process-stderr", (event) => {
     if (event.payload.name === name) {
-      callback(event.payload.data);
+      callback(event.payload.data, event.payload);
+    }
+  });
+}
+
+/** Stdout and stderr lines of a process spawned with `batch`, one call per batch. */
+export function onOutputBatch(
+  name: string,
+  callback: (lines: string[], payload: OutputBatchEventPayload) => void,
+): Promise<UnlistenFn> {
+  return listen<OutputBatchEventPayload>("js-process-output-batch", (event) => {
+    if (event.payload.name === name) {
+      callback(event.payload.lines, event.payload);
+    }
+  });
+}
+
+/** Messages from the IPC pipe or socket of a process spawned with `ipc` or `socket`. */
+export function onIpc(
+  name: string,
+  callback: (data: string, payload: IpcEventPayload) => void,
+): Promise<UnlistenFn> {
+  return listen<IpcEventPayload>("js-process-ipc", (event) => {
+    if (event.payload.name === name) {
+      callback(event.payload.data, event.payload);
+    }
+  });
+}
+
+/** Connections opening and closing on the socket of a process spawned with `socket: true`. */
+export function onConnection(
+  name: string,
+  callback: (payload: ConnectionEventPayload) => void,
+): Promise<UnlistenFn> {
+  return listen<ConnectionEventPayload>("js-process-connection", (event) => {
+    if (event.payload.name === name) {
+      callback(event.payload);
     }
   });
 }
 
 export function onExit(
   name: string,
-  callback: (code: number | null) => void,
+  callback: (code: number | null, payload: ExitEventPayload) => void,
 ): Promise<UnlistenFn> {
   return listen<ExitEventPayload>("js-process-exit", (event) => {
     if (event.payload.name === name) {
-      callback(event.payload.code);
+      callback(event.payload.code, event.payload);
+    }
+  });
+}
+
+export function onRestart(
+  name: string,
+  callback: (payload: RestartEventPayload) => void,
+): Promise<UnlistenFn> {
+  return listen<RestartEventPayload>("js-process-restart", (event) => {
+    if (event.payload.name === name) {
+      callback(event.payload);
+    }
+  });
+}
+
+export function onReady(
+  name: string,
+  callback: (payload: ReadinessEventPayload) => void,
+): Promise<UnlistenFn> {
+  return listen<ReadinessEventPayload>("js-process-ready", (event) => {
+    if (event.payload.name === name) {
+      callback(event.payload);
+    }
+  });
+}
+
+export function onReadinessFailed(
+  name: string,
+  callback: (payload: ReadinessEventPayload) => void,
+): Promise<UnlistenFn> {
+  return listen<ReadinessEventPayload>("js-process-readiness-failed", (event) => {
+    if (event.payload.name === name) {
+      callback(event.payload);
+    }
+  });
+}
+
+export function onCrashLoop(
+  name: string,
+  callback: (payload: CrashLoopEventPayload) => void,
+): Promise<UnlistenFn> {
+  return listen<CrashLoopEventPayload>("js-process-crash-loop", (event) => {
+    if (event.payload.name === name) {
+      callback(event.payload);
+    }
+  });
+}
+
+export function onThrottled(
+  name: string,
+  callback: (payload: ThrottleEventPayload) => void,
+): Promise<UnlistenFn> {
+  return listen<ThrottleEventPayload>("js-process-throttled", (event) => {
+    if (event.payload.name === name) {
+      callback(event.payload);
+    }
+  });
+}
+
+export function onDecodeError(
+  name: string,
+  callback: (payload: DecodeErrorEventPayload) => void,
+): Promise<UnlistenFn> {
+  return listen<DecodeErrorEventPayload>("js-process-decode-error", (event) => {
+    if (event.payload.name === name) {
+      callback(event.payload);
     }
   });
 }
@@ -130,17 +850,48 @@ export function onExit(
 
 type MessageListener = (data: string) => void;
 
+export interface JsRuntimeIoOptions {
+  /**
+   * When set, only this instance's output is read and writes fail once it has exited,
+   * instead of reaching a restarted instance.
+   */
+  instanceId?: number;
+  /**
+   * Must match the process's `framing` config. kkrpc messages are still newline-terminated
+   * on the JS side; the plugin converts them to and from frames.
+   */
+  framing?: Framing;
+  /**
+   * Use the dedicated IPC pipe or socket instead of stdio. Requires `ipc: true` or
+   * `socket: true` in the spawn config.
+   */
+  ipc?: boolean;
+  /**
+   * Replay output buffered before the channel was created, so nothing printed between
+   * `spawn` and `createChannel` is lost. Defaults to true.
+   */
+  replay?: boolean;
+}
+
 export class JsRuntimeIo implements IoInterface {
   readonly name: string;
   private processName: string;
+  private instanceId: number | undefined;
+  private framing: Framing;
+  private ipc: boolean;
+  private replay: boolean;
   private queue: string[] = [];
   private waitResolve: ((value: string | null) => void) | null = null;
   private listeners: Set<MessageListener> = new Set();
   private unlisten: UnlistenFn | null = null;
   private _isDestroyed = false;
 
-  constructor(processName: string) {
+  constructor(processName: string, options: JsRuntimeIoOptions = {}) {
     this.processName = processName;
+    this.instanceId = options.instanceId;
+    this.framing = options.framing ?? "newline";
+    this.ipc = options.ipc ?? false;
+    this.replay = options.replay ?? true;
     this.name = `tauri-js-runtime:${processName}`;
   }
 
@@ -149,34 +900,65 @@ export class JsRuntimeIo implements IoInterface {
   }
 
   async initialize(): Promise<void> {
-    this.unlisten = await listen<StdioEventPayload>(
-      "js-process-stdout",
+    // Subscribe rather than listen, so only this process's output reaches this webview
+    this.unlisten = await subscribe(
+      this.processName,
       (event) => {
-        if (event.payload.name !== this.processName) return;
+        let messages: string[];
+        if (event.event === (this.ipc ? "ipc" : "stdout")) {
+          messages = [(event.data as StdioEventPayload | IpcEventPayload).data];
+        } else if (
+          !this.ipc &&
+          event.event === "batch" &&
+          event.data.stream === "stdout"
+        ) {
+          messages = event.data.lines;
+        } else {
+          return;
+        }
+        if (
+          this.instanceId !== undefined &&
+          event.data.instanceId !== this.instanceId
+        )
+          return;
         if (this._isDestroyed) return;
 
-        // Re-append the newline that BufReader::lines() strips
-        const data = event.payload.data + "\n";
+        for (const message of messages) {
+          // Re-append the newline the reader strips; raw chunks arrive untouched
+          const data = this.framing === "raw" ? message : message + "\n";
 
-        // Dispatch to message listeners
-        for (const listener of this.listeners) {
-          listener(data);
-        }
+          // Dispatch to message listeners
+          for (const listener of this.listeners) {
+            listener(data);
+          }
 
-        // Feed the read queue
-        if (this.waitResolve) {
-          const resolve = this.waitResolve;
-          this.waitResolve = null;
-          resolve(data);
-        } else {
-          this.queue.push(data);
+          // Feed the read queue
+          if (this.waitResolve) {
+            const resolve = this.waitResolve;
+            this.waitResolve = null;
+            resolve(data);
+          } else {
+            this.queue.push(data);
+          }
         }
       },
+      this.replay ? 0 : undefined,
     );
   }
 
   async write(data: string): Promise<void> {
-    await writeStdin(this.processName, data);
+    // The plugin appends or prepends the frame delimiter itself
+    if (
+      (this.framing === "nul" || this.framing === "length-prefixed") &&
+      data.endsWith("\n")
+    ) {
+      data = data.slice(0, -1);
+    }
+    if (this.ipc) {
+      await writeIpc(this.processName, data, this.instanceId);
+    } else {
+      await writeStdin(this.processName, data, this.instanceId);
+    }
   }
 
   async read(): Promise<string | null> {
@@ -231,13 +1013,14 @@ export async function createChannel<
 >(
   processName: string,
   localApi?: LocalAPI,
+  options?: JsRuntimeIoOptions,
 ): Promise<{
   channel: any;
   api: RemoteAPI;
   io: JsRuntimeIo;
 }> {
   const { RPCChannel } = await import("kkrpc/browser");
-  const io = new JsRuntimeIo(processName);
+  const io = new JsRuntimeIo(processName, options);
   await io.initialize();
   const channel = new RPCChannel<LocalAPI, RemoteAPI>(io, {
     expose: localApi ?? ({} as LocalAPI),
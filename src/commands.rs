@@ -1,4 +1,5 @@
-use tauri::{command, AppHandle, Runtime};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use tauri::{command, ipc::Channel, AppHandle, Runtime, Webview};
 
 use std::collections::HashMap;
 
@@ -16,12 +17,25 @@ pub(crate) async fn spawn<R: Runtime>(
 }
 
 #[command]
-pub(crate) async fn kill<R: Runtime>(app: AppHandle<R>, name: String) -> Result<()> {
-    app.js().kill(name).await
+pub(crate) async fn exec<R: Runtime>(
+    app: AppHandle<R>,
+    config: SpawnConfig,
+    options: Option<ExecOptions>,
+) -> Result<ExecResult> {
+    app.js().exec(config, options.unwrap_or_default()).await
+}
+
+#[command]
+pub(crate) async fn kill<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    instance_id: Option<u64>,
+) -> Result<ShutdownResult> {
+    app.js().kill(name, instance_id).await
 }
 
 #[command]
-pub(crate) async fn kill_all<R: Runtime>(app: AppHandle<R>) -> Result<()> {
+pub(crate) async fn kill_all<R: Runtime>(app: AppHandle<R>) -> Result<Vec<ShutdownResult>> {
     app.js().kill_all().await
 }
 
@@ -30,8 +44,9 @@ pub(crate) async fn restart<R: Runtime>(
     app: AppHandle<R>,
     name: String,
     config: Option<SpawnConfig>,
+    instance_id: Option<u64>,
 ) -> Result<ProcessInfo> {
-    app.js().restart(name, config).await
+    app.js().restart(name, config, instance_id).await
 }
 
 #[command]
@@ -40,8 +55,59 @@ pub(crate) async fn list_processes<R: Runtime>(app: AppHandle<R>) -> Result<Vec<
 }
 
 #[command]
-pub(crate) async fn get_status<R: Runtime>(app: AppHandle<R>, name: String) -> Result<ProcessInfo> {
-    app.js().get_status(name).await
+pub(crate) async fn list_orphans<R: Runtime>(app: AppHandle<R>) -> Result<Vec<OrphanInfo>> {
+    app.js().list_orphans().await
+}
+
+#[command]
+pub(crate) async fn get_status<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    instance_id: Option<u64>,
+) -> Result<ProcessInfo> {
+    app.js().get_status(name, instance_id).await
+}
+
+#[command]
+pub(crate) async fn wait<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    timeout_ms: Option<u64>,
+    instance_id: Option<u64>,
+) -> Result<ExitEventPayload> {
+    app.js().wait(name, timeout_ms, instance_id).await
+}
+
+#[command]
+pub(crate) async fn wait_for_output<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    pattern: String,
+    options: Option<WaitForOutputOptions>,
+) -> Result<OutputMatch> {
+    app.js()
+        .wait_for_output(name, pattern, options.unwrap_or_default())
+        .await
+}
+
+#[command]
+pub(crate) async fn subscribe<R: Runtime>(
+    app: AppHandle<R>,
+    webview: Webview<R>,
+    name: String,
+    on_event: Channel<ProcessEvent>,
+    from_seq: Option<u64>,
+) -> Result<u64> {
+    let window = webview.window().label().to_string();
+    let webview = webview.label().to_string();
+    app.js()
+        .subscribe(name, webview, window, on_event, from_seq)
+        .await
+}
+
+#[command]
+pub(crate) async fn unsubscribe<R: Runtime>(app: AppHandle<R>, subscription_id: u64) -> Result<()> {
+    app.js().unsubscribe(subscription_id).await
 }
 
 #[command]
@@ -49,8 +115,66 @@ pub(crate) async fn write_stdin<R: Runtime>(
     app: AppHandle<R>,
     name: String,
     data: String,
+    instance_id: Option<u64>,
+) -> Result<()> {
+    app.js().write_stdin(name, data, instance_id).await
+}
+
+/// Binary counterpart of [`write_stdin`]; `data` is base64-encoded.
+#[command]
+pub(crate) async fn write_stdin_bytes<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    data: String,
+    instance_id: Option<u64>,
+) -> Result<()> {
+    let data = BASE64
+        .decode(data)
+        .map_err(|e| crate::Error::StdinWriteError(name.clone(), format!("invalid base64: {e}")))?;
+    app.js().write_stdin_bytes(name, data, instance_id).await
+}
+
+#[command]
+pub(crate) async fn close_stdin<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    instance_id: Option<u64>,
+) -> Result<()> {
+    app.js().close_stdin(name, instance_id).await
+}
+
+#[command]
+pub(crate) async fn signal<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    signal: String,
+    instance_id: Option<u64>,
+) -> Result<()> {
+    app.js().signal(name, signal, instance_id).await
+}
+
+#[command]
+pub(crate) async fn resize<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    cols: u16,
+    rows: u16,
+    instance_id: Option<u64>,
+) -> Result<()> {
+    app.js().resize(name, cols, rows, instance_id).await
+}
+
+#[command]
+pub(crate) async fn write_ipc<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    data: String,
+    instance_id: Option<u64>,
+    connection_id: Option<u64>,
 ) -> Result<()> {
-    app.js().write_stdin(name, data).await
+    app.js()
+        .write_ipc(name, data, instance_id, connection_id)
+        .await
 }
 
 #[command]
@@ -1,48 +1,872 @@
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use serde::de::DeserializeOwned;
-use tauri::{plugin::PluginApi, AppHandle, Emitter, Runtime};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, ChildStdin, Command};
-use tokio::sync::Mutex;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use tauri::{ipc::Channel, plugin::PluginApi, AppHandle, Emitter, Manager, Runtime};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::{mpsc, oneshot, watch, Mutex};
 
+use crate::framing;
+use crate::ipc::{self, IpcSocket};
+use crate::matcher::OutputPattern;
 use crate::models::*;
+use crate::pty::{self, PtyMaster};
+use crate::readiness::{self, Probe};
+use crate::registry::{self, Registry, RegistryRecord};
+use crate::subscriptions::Subscriptions;
+use crate::throttle::OutputLimiter;
+use crate::write_queue::WriteQueue;
+use crate::JsExt;
+
+/// Grace period before a stopping process is force-killed, unless overridden in its config.
+const DEFAULT_SHUTDOWN_TIMEOUT_MS: u64 = 5000;
+
+/// Signals accepted in `ShutdownConfig::signal`.
+const SHUTDOWN_SIGNALS: &[&str] = &["SIGTERM", "SIGINT", "SIGHUP", "SIGQUIT"];
+
+/// Signals that can be sent with [`Js::signal`]. SIGKILL is left to `kill`, which records why
+/// the process exited.
+const SIGNALS: &[&str] = &[
+    "SIGHUP", "SIGINT", "SIGQUIT", "SIGTERM", "SIGUSR1", "SIGUSR2", "SIGSTOP", "SIGCONT",
+    "SIGWINCH",
+];
+
+/// Restart policy defaults, see [`RestartPolicy`].
+const DEFAULT_MAX_RETRIES: u32 = 5;
+const DEFAULT_RESTART_WINDOW_MS: u64 = 60_000;
+const DEFAULT_INITIAL_BACKOFF_MS: u64 = 500;
+const DEFAULT_MAX_BACKOFF_MS: u64 = 30_000;
+
+/// How often a process group is checked for members left behind by its leader.
+#[cfg(unix)]
+const GROUP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How often processes left behind by a previous run are checked for having exited, as they
+/// are not our children and cannot be waited on.
+const ORPHAN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Grace period for orphans to exit on SIGTERM before they are killed, see
+/// [`OrphanPolicy::Terminate`].
+const ORPHAN_TERMINATE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Exited processes kept around so `get_status` can still report why they stopped.
+const MAX_RETAINED_EXITED: usize = 32;
+
+/// Earlier instances of a name kept around so `wait` can still report how they exited once
+/// the name has been spawned again.
+const MAX_REPLACED_INSTANCES: usize = 8;
+
+/// Output batching defaults, see [`BatchConfig`].
+const DEFAULT_BATCH_INTERVAL_MS: u64 = 16;
+const DEFAULT_BATCH_BYTES: usize = 64 * 1024;
+
+/// Lines a reader can get ahead of its batcher before it stops reading.
+const BATCH_QUEUE_LEN: usize = 1024;
+
+/// Default for `ExecOptions::max_output_bytes`.
+const DEFAULT_EXEC_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// How long the exit watcher waits for the output readers to reach the end of their pipes
+/// before it publishes the exit. Children the process left behind can keep the pipes open.
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Readiness defaults, see [`ReadinessConfig`].
+const DEFAULT_READINESS_TIMEOUT_MS: u64 = 30_000;
+const DEFAULT_PROBE_INTERVAL_MS: u64 = 250;
+
+/// Bounds on how long a single TCP or HTTP probe attempt may take, which is otherwise the
+/// probe interval.
+const MIN_PROBE_ATTEMPT_TIMEOUT: Duration = Duration::from_secs(1);
+const MAX_PROBE_ATTEMPT_TIMEOUT: Duration = Duration::from_secs(5);
 
 struct ProcessEntry {
-    child: Child,
-    stdin: Option<ChildStdin>,
-    config: SpawnConfig,
+    info: ProcessInfo,
+    /// `None` once the child has exited or is being stopped
+    stdin: Option<WriteQueue>,
+    /// Parent end of the IPC pipe, if the process was spawned with `ipc`; `None` once the
+    /// child has exited or is being stopped
+    ipc: Option<WriteQueue>,
+    /// Open connections to the process's socket, by connection id
+    connections: HashMap<u64, WriteQueue>,
+    /// Keeps the process's socket open; dropping it closes the socket and its connections
+    socket_guard: Option<watch::Sender<()>>,
+    /// Asks the exit watcher, which owns the `Child`, to force-kill it
+    kill_tx: Option<oneshot::Sender<()>>,
+    /// Set when the process is stopped on request, so the exit watcher reports why it exited
+    stop_reason: Arc<OnceLock<ExitReason>>,
+    /// Set by the exit watcher as soon as the child has been reaped, after which its pid may
+    /// be reused; the exit is published once its remaining output has been drained
+    reaped_rx: watch::Receiver<bool>,
+    /// Set by the exit watcher once the exit has been recorded and published
+    exit_rx: watch::Receiver<Option<Exited>>,
+    /// Set once the readiness probe, if the process has one, has succeeded or given up
+    ready_rx: watch::Receiver<Option<ReadinessEventPayload>>,
+    /// Rate limit shared by the stdout and stderr readers, if the process has one
+    limiter: Option<Arc<OutputLimiter>>,
+    /// State to return to once a suspended process is sent SIGCONT
+    resume_state: Option<ProcessState>,
+    /// Parent side of the process's pseudo-terminal, if it was spawned with `pty`; `None` once
+    /// the child has exited
+    pty: Option<PtyMaster>,
+    /// Start time of a process reattached from a previous run. It is not our child and can
+    /// exit unnoticed, so its pid is checked against this before every signal.
+    orphan_start_time: Option<String>,
+    /// Exits of the instances this one replaced, oldest first, by instance id
+    replaced: VecDeque<(u64, watch::Receiver<Option<Exited>>)>,
+}
+
+impl ProcessEntry {
+    /// The exits of this instance and the ones it replaced, for the instance replacing it.
+    fn take_replaced(&mut self) -> VecDeque<(u64, watch::Receiver<Option<Exited>>)> {
+        let mut replaced = std::mem::take(&mut self.replaced);
+        replaced.push_back((self.info.instance_id, self.exit_rx.clone()));
+        if replaced.len() > MAX_REPLACED_INSTANCES {
+            replaced.pop_front();
+        }
+        replaced
+    }
+
+    /// Whether this entry is the instance the caller asked for; `None` matches any instance.
+    fn matches(&self, instance_id: Option<u64>) -> bool {
+        instance_id.map_or(true, |id| id == self.info.instance_id)
+    }
+
+    /// The process's info, including how much output the rate limit has held back so far.
+    fn snapshot(&self) -> ProcessInfo {
+        let mut info = self.info.clone();
+        if let Some(limiter) = &self.limiter {
+            info.output_stats = limiter.stats();
+        }
+        info
+    }
+
+    fn set_state(&mut self, state: ProcessState) {
+        self.info.state = state;
+        self.info.running = state.is_alive();
+    }
+
+    /// Moves a live process to `Stopping` and hands out what is needed to shut it down
+    /// outside the lock. A pending restart is cancelled instead; exited processes yield
+    /// `None`. Either way the process's socket is closed.
+    fn begin_stop(&mut self, name: &str, reason: ExitReason) -> Option<StopHandle> {
+        self.connections.clear();
+        self.socket_guard = None;
+        match self.info.state {
+            ProcessState::Restarting => {
+                self.set_state(settled_state(self.info.exit_reason));
+                None
+            }
+            state if state.is_alive() => {
+                let _ = self.stop_reason.set(reason);
+                self.set_state(ProcessState::Stopping);
+                self.resume_state = None;
+                // Close the IPC pipe right away; the shutdown message goes over stdin
+                self.ipc = None;
+                Some(StopHandle {
+                    name: name.to_string(),
+                    instance_id: self.info.instance_id,
+                    pid: self.info.pid,
+                    stdin: self.stdin.take(),
+                    kill_tx: self.kill_tx.take(),
+                    reaped_rx: self.reaped_rx.clone(),
+                    exit_rx: self.exit_rx.clone(),
+                    shutdown: self.info.config.shutdown.clone().unwrap_or_default(),
+                    framing: stdio_framing(&self.info.config),
+                    suspended: state == ProcessState::Suspended,
+                    group: self.info.config.process_group.unwrap_or(false),
+                    orphan_start_time: self.orphan_start_time.clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Result for a kill that found nothing left to stop.
+    fn already_exited(&self, name: String) -> ShutdownResult {
+        ShutdownResult {
+            name,
+            instance_id: self.info.instance_id,
+            outcome: ShutdownOutcome::AlreadyExited,
+            code: self.info.exit_code,
+            signal: self.info.exit_signal,
+            elapsed_ms: 0,
+        }
+    }
+}
+
+/// A process taken out of service by kill/kill_all/restart, stopped outside the map lock.
+struct StopHandle {
+    name: String,
+    instance_id: u64,
+    pid: Option<u32>,
+    stdin: Option<WriteQueue>,
+    kill_tx: Option<oneshot::Sender<()>>,
+    reaped_rx: watch::Receiver<bool>,
+    exit_rx: watch::Receiver<Option<Exited>>,
+    shutdown: ShutdownConfig,
+    framing: Framing,
+    /// Whether the process was stopped with SIGSTOP and needs SIGCONT to act on the signal
+    suspended: bool,
+    /// Whether the process leads its own process group, which is stopped along with it
+    group: bool,
+    orphan_start_time: Option<String>,
+}
+
+impl StopHandle {
+    fn has_exited(&self) -> bool {
+        *self.reaped_rx.borrow()
+    }
+
+    /// The pid to signal, while it still refers to the process.
+    fn live_pid(&self) -> Option<u32> {
+        self.pid.filter(|&pid| {
+            !self.has_exited() && is_same_process(pid, self.orphan_start_time.as_deref())
+        })
+    }
+
+    /// Resolves once the exit watcher has reaped the child.
+    async fn reaped(&mut self) {
+        let _ = self.reaped_rx.wait_for(|&reaped| reaped).await;
+    }
+
+    /// Resolves once the exit watcher has published the exit.
+    async fn exited(&mut self) -> Option<ExitStatus> {
+        self.exit_rx
+            .wait_for(|exited| exited.is_some())
+            .await
+            .ok()
+            .and_then(|exited| exited.as_ref().and_then(|exited| exited.status))
+    }
+
+    fn force_kill(&mut self) {
+        if let Some(kill_tx) = self.kill_tx.take() {
+            let _ = kill_tx.send(());
+        }
+    }
+}
+
+/// How an exit watcher tells the rest of the plugin that its instance has ended.
+struct ExitSenders {
+    /// Set as soon as the child has been reaped
+    reaped: watch::Sender<bool>,
+    /// Set once the exit has been recorded and published
+    exited: watch::Sender<Option<Exited>>,
+}
+
+impl ExitSenders {
+    fn channel() -> (Self, watch::Receiver<bool>, watch::Receiver<Option<Exited>>) {
+        let (reaped, reaped_rx) = watch::channel(false);
+        let (exited, exit_rx) = watch::channel(None);
+        (Self { reaped, exited }, reaped_rx, exit_rx)
+    }
+}
+
+/// How an instance ended, published by its exit watcher.
+#[derive(Clone)]
+struct Exited {
+    /// `None` if waiting on the child failed, and for orphans, which cannot be waited on
+    status: Option<ExitStatus>,
+    event: ExitEventPayload,
+}
+
+/// Automatic restarts carried over from one instance of a process to the next.
+#[derive(Default)]
+struct RestartHistory {
+    /// Restarts since the process was last spawned explicitly
+    total: u32,
+    /// When each restart within the current window happened
+    recent: VecDeque<Instant>,
 }
 
 pub struct Js<R: Runtime> {
     app: AppHandle<R>,
+    /// Live processes, plus exited ones until they are replaced or pruned
     processes: Arc<Mutex<HashMap<String, ProcessEntry>>>,
     runtime_paths: Arc<Mutex<HashMap<String, String>>>,
+    next_instance_id: AtomicU64,
+    subscriptions: Subscriptions,
+    registry: Arc<Registry>,
+    /// Processes left running by previous runs, found at startup
+    orphans: Vec<OrphanInfo>,
 }
 
-pub fn init<R: Runtime, C: DeserializeOwned>(
+pub fn init<R: Runtime>(
     app: &AppHandle<R>,
-    _api: PluginApi<R, C>,
+    api: PluginApi<R, Option<PluginConfig>>,
 ) -> crate::Result<Js<R>> {
-    Ok(Js {
+    let policy = api
+        .config()
+        .as_ref()
+        .and_then(|config| config.orphans)
+        .unwrap_or_default();
+    let dir = app
+        .path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join("js-processes"));
+    let (registry, found) = Registry::open(dir);
+    let mut js = Js {
         app: app.clone(),
         processes: Arc::new(Mutex::new(HashMap::new())),
         runtime_paths: Arc::new(Mutex::new(HashMap::new())),
-    })
+        next_instance_id: AtomicU64::new(1),
+        subscriptions: Subscriptions::default(),
+        registry: Arc::new(registry),
+        orphans: Vec::new(),
+    };
+    js.orphans = js.adopt_orphans(found, policy);
+    Ok(js)
 }
 
 impl<R: Runtime> Js<R> {
     pub async fn spawn(&self, name: String, config: SpawnConfig) -> crate::Result<ProcessInfo> {
-        // Check if process already exists
-        {
+        let wait = config
+            .readiness
+            .as_ref()
+            .is_some_and(|r| r.wait.unwrap_or(false));
+        let info = self
+            .start(name, config, RestartHistory::default(), None)
+            .await?;
+        if wait {
+            self.wait_ready(info.name, info.instance_id).await
+        } else {
+            Ok(info)
+        }
+    }
+
+    /// Waits for the readiness probe of an instance to finish, and returns the instance's
+    /// info once it is ready. If the probe gave up, the instance is stopped, since the caller
+    /// never learns its id, and this fails with [`crate::Error::NotReady`].
+    async fn wait_ready(&self, name: String, instance_id: u64) -> crate::Result<ProcessInfo> {
+        let mut ready_rx = {
             let procs = self.processes.lock().await;
-            if procs.contains_key(&name) {
-                return Err(crate::Error::ProcessAlreadyExists(name));
+            Self::instance(&procs, &name, Some(instance_id))?
+                .ready_rx
+                .clone()
+        };
+        let outcome = ready_rx
+            .wait_for(|outcome| outcome.is_some())
+            .await
+            .ok()
+            .and_then(|outcome| outcome.clone());
+        match outcome {
+            Some(ReadinessEventPayload {
+                error: Some(error), ..
+            }) => {
+                let _ = self.kill(name.clone(), Some(instance_id)).await;
+                Err(crate::Error::NotReady(name, error))
+            }
+            Some(_) => self.get_status(name, Some(instance_id)).await,
+            // The probe only goes away without an outcome if the app is shutting down
+            None => Err(crate::Error::ProcessNotRunning(name)),
+        }
+    }
+
+    /// Runs `config` to completion without tracking it as a named process, and returns its
+    /// exit status and output. Settings for long-lived processes (restart policy, shutdown,
+    /// replay, batching, rate limit, readiness) do not apply; `ipc`, `socket` and `pty` are
+    /// rejected.
+    pub async fn exec(
+        &self,
+        config: SpawnConfig,
+        options: ExecOptions,
+    ) -> crate::Result<ExecResult> {
+        if config.ipc.unwrap_or(false)
+            || config.socket.unwrap_or(false)
+            || config.pty.unwrap_or(false)
+        {
+            return Err(crate::Error::InvalidConfig(
+                "'ipc', 'socket' and 'pty' cannot be used with exec".to_string(),
+            ));
+        }
+        let (mut cmd, _) = self.command(&config).await?;
+        cmd.stdin(if options.stdin.is_some() {
+            std::process::Stdio::piped()
+        } else {
+            std::process::Stdio::null()
+        });
+        cmd.stdout(std::process::Stdio::piped());
+        cmd.stderr(std::process::Stdio::piped());
+        // Don't leave the process running if the caller goes away
+        cmd.kill_on_drop(true);
+        let group = config.process_group.unwrap_or(false);
+
+        let started = Instant::now();
+        let mut child = cmd.spawn().map_err(crate::Error::Io)?;
+        let pid = child.id();
+        let max_bytes = options
+            .max_output_bytes
+            .unwrap_or(DEFAULT_EXEC_OUTPUT_BYTES);
+        let mut stdout = OutputCapture::new(max_bytes);
+        let mut stderr = OutputCapture::new(max_bytes);
+        let stdin = child.stdin.take().zip(options.stdin);
+        let (child_stdout, child_stderr) = (child.stdout.take(), child.stderr.take());
+        let run = async {
+            let write_stdin = async {
+                if let Some((mut pipe, data)) = stdin {
+                    // The process may exit without reading all of it; dropping the pipe closes it
+                    let _ = pipe.write_all(data.as_bytes()).await;
+                }
+            };
+            let (status, ..) = tokio::join!(
+                child.wait(),
+                stdout.read_from(child_stdout),
+                stderr.read_from(child_stderr),
+                write_stdin,
+            );
+            status
+        };
+        let finished = match options.timeout_ms {
+            Some(ms) => tokio::time::timeout(Duration::from_millis(ms), run)
+                .await
+                .ok(),
+            None => Some(run.await),
+        };
+        let (status, timed_out) = match finished {
+            Some(status) => (status, false),
+            None => {
+                #[cfg(unix)]
+                if let Some(pid) = pid.filter(|_| group) {
+                    // SAFETY: the child has not been reaped, so its group still exists
+                    unsafe {
+                        libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+                    }
+                }
+                let _ = child.start_kill();
+                (child.wait().await, true)
+            }
+        };
+        let status = status.map_err(crate::Error::Io)?;
+
+        Ok(ExecResult {
+            code: status.code(),
+            signal: exit_signal(&status),
+            stdout_truncated: stdout.truncated,
+            stderr_truncated: stderr.truncated,
+            stdout: stdout.finish(config.stdout_encoding.unwrap_or_default()),
+            stderr: stderr.finish(config.stderr_encoding.unwrap_or_default()),
+            timed_out,
+            duration_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Spawns a new instance of `name`, replacing an exited entry if there is one. An explicit
+    /// spawn also supersedes a pending automatic restart. `replaces` is set for automatic
+    /// restarts, which only go ahead while that instance is still waiting to be restarted.
+    async fn start(
+        &self,
+        name: String,
+        config: SpawnConfig,
+        history: RestartHistory,
+        replaces: Option<u64>,
+    ) -> crate::Result<ProcessInfo> {
+        if let Some(signal) = config.shutdown.as_ref().and_then(|s| s.signal.as_ref()) {
+            if !SHUTDOWN_SIGNALS.contains(&signal.as_str()) {
+                return Err(crate::Error::InvalidConfig(format!(
+                    "unsupported shutdown signal: {}",
+                    signal
+                )));
+            }
+        }
+        if config
+            .rate_limit
+            .as_ref()
+            .is_some_and(|r| r.sample_every == Some(0))
+        {
+            return Err(crate::Error::InvalidConfig(
+                "'sampleEvery' must be at least 1".to_string(),
+            ));
+        }
+        if config
+            .rate_limit
+            .as_ref()
+            .is_some_and(|r| r.max_spill_bytes == Some(0))
+        {
+            return Err(crate::Error::InvalidConfig(
+                "'maxSpillBytes' must be at least 1".to_string(),
+            ));
+        }
+        if config
+            .write_queue
+            .as_ref()
+            .is_some_and(|q| q.max_pending == Some(0))
+        {
+            return Err(crate::Error::InvalidConfig(
+                "'maxPending' must be at least 1".to_string(),
+            ));
+        }
+        let probe = config.readiness.as_ref().map(Probe::new).transpose()?;
+
+        let (mut cmd, command_line) = self.command(&config).await?;
+        cmd.stdin(std::process::Stdio::piped());
+        cmd.stdout(std::process::Stdio::piped());
+        cmd.stderr(std::process::Stdio::piped());
+
+        let use_pty = config.pty.unwrap_or(false);
+        if config.ipc.unwrap_or(false) && config.socket.unwrap_or(false) {
+            return Err(crate::Error::InvalidConfig(
+                "'ipc' and 'socket' cannot be used together".to_string(),
+            ));
+        }
+        let pending_ipc = if config.ipc.unwrap_or(false) {
+            Some(ipc::attach(&mut cmd).map_err(crate::Error::Io)?)
+        } else {
+            None
+        };
+        let pending_pty = if use_pty {
+            // Apps launched from a desktop environment often have no TERM to pass on
+            if !config
+                .env
+                .as_ref()
+                .is_some_and(|env| env.contains_key("TERM"))
+            {
+                cmd.env("TERM", "xterm-256color");
+            }
+            let size = config.pty_size.unwrap_or_default();
+            Some(pty::attach(&mut cmd, size).map_err(crate::Error::Io)?)
+        } else {
+            None
+        };
+
+        // Check for an existing process and insert the new one under a single lock, so two
+        // concurrent spawns of the same name cannot both succeed
+        let mut procs = self.processes.lock().await;
+        let existing = procs.get(&name).map(|e| (e.info.instance_id, e.info.state));
+        let superseded = match (existing, replaces) {
+            (Some((_, state)), _) if state.is_alive() => true,
+            (Some((id, state)), Some(replaces)) => {
+                id != replaces || state != ProcessState::Restarting
             }
+            (None, Some(_)) => true,
+            _ => false,
+        };
+        if superseded {
+            return Err(crate::Error::ProcessAlreadyExists(name));
+        }
+
+        let instance_id = self.next_instance_id.fetch_add(1, Ordering::Relaxed);
+        let socket = if config.socket.unwrap_or(false) {
+            let socket =
+                IpcSocket::bind(self.socket_path(instance_id)?).map_err(crate::Error::Io)?;
+            cmd.env(ipc::SOCKET_ENV, socket.path());
+            Some(socket)
+        } else {
+            None
+        };
+        let limiter = match config.rate_limit {
+            Some(ref rate_limit) => Some(Arc::new(OutputLimiter::new(
+                rate_limit,
+                self.spill_path(&name, instance_id)?,
+            ))),
+            None => None,
+        };
+
+        let mut child = cmd.spawn().map_err(crate::Error::Io)?;
+        let (ipc_writer, ipc_reader) = match pending_ipc.map(|pending| pending.finish()) {
+            Some(Ok((writer, reader))) => (
+                Some(WriteQueue::spawn(writer, config.write_queue.as_ref())),
+                Some(reader),
+            ),
+            Some(Err(e)) => {
+                let _ = child.start_kill();
+                return Err(crate::Error::Io(e));
+            }
+            None => (None, None),
+        };
+
+        let pid = child.id();
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let (pty_reader, pty_master, stdin) = match pending_pty.map(|pending| pending.finish()) {
+            Some((reader, writer, master)) => (
+                Some(reader),
+                Some(master),
+                Some(WriteQueue::spawn(writer, config.write_queue.as_ref())),
+            ),
+            None => (
+                None,
+                None,
+                child
+                    .stdin
+                    .take()
+                    .map(|stdin| WriteQueue::spawn(stdin, config.write_queue.as_ref())),
+            ),
+        };
+        let (kill_tx, mut kill_rx) = oneshot::channel();
+        let (exit_senders, reaped_rx, exit_rx) = ExitSenders::channel();
+        let (ready_tx, ready_rx) = watch::channel(None);
+        let stop_reason = Arc::new(OnceLock::new());
+        let (socket_guard, socket_closed) = watch::channel(());
+
+        let info = ProcessInfo {
+            name: name.clone(),
+            instance_id,
+            pid,
+            state: ProcessState::Starting,
+            running: true,
+            started_at: now_ms(),
+            exited_at: None,
+            exit_code: None,
+            exit_signal: None,
+            exit_reason: None,
+            restart_count: history.total,
+            config: config.clone(),
+            output_stats: OutputStats::default(),
+        };
+        let entry = ProcessEntry {
+            info: info.clone(),
+            stdin,
+            ipc: ipc_writer,
+            connections: HashMap::new(),
+            socket_guard: socket.as_ref().map(|_| socket_guard),
+            kill_tx: Some(kill_tx),
+            stop_reason: stop_reason.clone(),
+            reaped_rx,
+            exit_rx: exit_rx.clone(),
+            ready_rx,
+            limiter: limiter.clone(),
+            resume_state: None,
+            pty: pty_master,
+            orphan_start_time: None,
+            replaced: procs
+                .get_mut(&name)
+                .map(ProcessEntry::take_replaced)
+                .unwrap_or_default(),
+        };
+
+        procs.insert(name.clone(), entry);
+        drop(procs);
+        self.subscriptions
+            .reset_replay(&name, config.replay.as_ref());
+
+        // Written to disk, so only once the process map is unlocked; the exit watcher that
+        // removes the record is not running yet
+        let start_time = pid.and_then(registry::start_time);
+        if let (Some(pid), Some(start_time)) = (pid, &start_time) {
+            self.registry.add(RegistryRecord {
+                name: name.clone(),
+                instance_id,
+                pid,
+                start_time: start_time.clone(),
+                started_at: info.started_at,
+                command: command_line,
+                cwd: config.cwd.clone(),
+                process_group: config.process_group.unwrap_or(false),
+            });
+        }
+
+        // Spawn stdout/stderr reader tasks
+        let mut readers = Vec::new();
+        if let Some(reader) = pty_reader {
+            readers.push(spawn_output_reader(
+                self.app.clone(),
+                OutputSource {
+                    limiter: limiter.clone(),
+                    ..OutputSource::new(&name, instance_id, StdioStream::Stdout, &config)
+                },
+                reader,
+                Framing::Raw,
+                config.stdout_encoding.unwrap_or_default(),
+            ));
+        }
+        if let Some(stdout) = stdout {
+            readers.push(spawn_output_reader(
+                self.app.clone(),
+                OutputSource {
+                    limiter: limiter.clone(),
+                    ..OutputSource::new(&name, instance_id, StdioStream::Stdout, &config)
+                },
+                stdout,
+                stdio_framing(&config),
+                config.stdout_encoding.unwrap_or_default(),
+            ));
+        }
+        if let Some(reader) = ipc_reader {
+            readers.push(spawn_output_reader(
+                self.app.clone(),
+                OutputSource::new(&name, instance_id, StdioStream::Ipc, &config),
+                reader,
+                config.framing.unwrap_or_default(),
+                OutputEncoding::Lossy,
+            ));
+        }
+        if let Some(stderr) = stderr {
+            readers.push(spawn_output_reader(
+                self.app.clone(),
+                OutputSource {
+                    limiter,
+                    ..OutputSource::new(&name, instance_id, StdioStream::Stderr, &config)
+                },
+                stderr,
+                Framing::Newline,
+                config.stderr_encoding.unwrap_or_default(),
+            ));
+        }
+
+        if let Some(socket) = socket {
+            self.spawn_socket_listener(
+                OutputSource::new(&name, instance_id, StdioStream::Ipc, &config),
+                socket,
+                socket_closed,
+                config.framing.unwrap_or_default(),
+            );
+        }
+
+        // Spawn exit watcher task. It owns the child and awaits its exit directly, so the
+        // process map is only locked to record the exit, never while waiting.
+        // It emits exactly one `js-process-exit` per instance, however the instance ended.
+        {
+            let app = self.app.clone();
+            let proc_name = name.clone();
+            let processes = self.processes.clone();
+            let registry = self.registry.clone();
+            let config = config.clone();
+            let started = Instant::now();
+            tauri::async_runtime::spawn(async move {
+                let status = tokio::select! {
+                    status = child.wait() => status,
+                    // A dropped sender means the entry was discarded, not a kill request
+                    Ok(()) = &mut kill_rx => {
+                        #[cfg(unix)]
+                        if let Some(pid) = pid.filter(|_| config.process_group.unwrap_or(false)) {
+                            // SAFETY: the child has not been reaped, so its group still exists
+                            unsafe {
+                                libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+                            }
+                        }
+                        let _ = child.start_kill();
+                        child.wait().await
+                    }
+                };
+                let status = status.ok();
+                let _ = exit_senders.reaped.send(true);
+                // Let the readers publish the last of the output before the exit
+                let drained = async {
+                    for reader in readers {
+                        let _ = reader.await;
+                    }
+                };
+                let _ = tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, drained).await;
+                if let (Some(pid), Some(start_time)) = (pid, &start_time) {
+                    registry.remove(pid, start_time);
+                }
+                let code = status.and_then(|s| s.code());
+                let signal = status.and_then(|s| exit_signal(&s));
+
+                let requested = stop_reason.get().copied();
+                let reason = requested.unwrap_or(match status {
+                    Some(status) if status.success() => ExitReason::Exited,
+                    _ => ExitReason::Crashed,
+                });
+                let mode = config
+                    .restart_policy
+                    .as_ref()
+                    .map(|p| p.mode)
+                    .unwrap_or_default();
+                let should_restart = requested.is_none()
+                    && match mode {
+                        RestartMode::Never => false,
+                        RestartMode::OnFailure => code != Some(0),
+                        RestartMode::Always => true,
+                    };
+
+                // Record the exit, unless the name already belongs to a newer instance
+                {
+                    let mut procs = processes.lock().await;
+                    if let Some(entry) = procs
+                        .get_mut(&proc_name)
+                        .filter(|entry| entry.info.instance_id == instance_id)
+                    {
+                        let state = if should_restart || reason == ExitReason::Restart {
+                            ProcessState::Restarting
+                        } else {
+                            settled_state(Some(reason))
+                        };
+                        entry.set_state(state);
+                        entry.info.exited_at = Some(now_ms());
+                        entry.info.exit_code = code;
+                        entry.info.exit_signal = signal;
+                        entry.info.exit_reason = Some(reason);
+                        entry.stdin = None;
+                        entry.ipc = None;
+                        entry.kill_tx = None;
+                        entry.resume_state = None;
+                        entry.pty = None;
+                    }
+                    for name in prune_exited(&mut procs) {
+                        app.js().subscriptions.remove_replay(&name);
+                    }
+                }
+
+                let payload = ExitEventPayload {
+                    name: proc_name.clone(),
+                    instance_id,
+                    code,
+                    signal,
+                    reason,
+                    uptime_ms: started.elapsed().as_millis() as u64,
+                };
+                publish(
+                    &app,
+                    config.broadcast.unwrap_or(true),
+                    ProcessEvent::Exit(payload.clone()),
+                );
+
+                // Publish the status last, so a caller waiting on the exit sees the exit
+                // recorded and the exit event sent before it starts a new instance.
+                let _ = exit_senders.exited.send(Some(Exited {
+                    status,
+                    event: payload,
+                }));
+
+                if should_restart {
+                    restart_after_exit(app, proc_name, instance_id, config, code, history).await;
+                }
+            });
+        }
+
+        let probing = probe.is_some();
+        if let (Some(probe), Some(readiness)) = (probe, config.readiness.clone()) {
+            tauri::async_runtime::spawn(watch_readiness(
+                self.app.clone(),
+                ReadinessProbe {
+                    name: name.clone(),
+                    instance_id,
+                    probe,
+                    config: readiness,
+                    broadcast: config.broadcast.unwrap_or(true),
+                },
+                exit_rx,
+                ready_tx,
+            ));
+        }
+
+        // Output and exit watchers are attached, the process is now considered running; with
+        // a readiness probe it stays starting until the probe settles it
+        if let Some(entry) = self
+            .processes
+            .lock()
+            .await
+            .get_mut(&name)
+            .filter(|entry| entry.info.instance_id == instance_id)
+        {
+            if entry.info.state == ProcessState::Starting && !probing {
+                entry.set_state(ProcessState::Running);
+            }
+            return Ok(entry.info.clone());
         }
+        Ok(info)
+    }
 
-        // Build the command
+    /// Program and arguments to run for `config`: the sidecar, command or runtime (honoring
+    /// custom runtime paths) with its script, followed by `args`.
+    async fn resolve_program(&self, config: &SpawnConfig) -> crate::Result<(String, Vec<String>)> {
         let (program, mut args_vec) = if let Some(ref sidecar) = config.sidecar {
             let path = self.resolve_sidecar(sidecar)?;
             (path.to_string_lossy().to_string(), Vec::new())
@@ -99,12 +923,17 @@ impl<R: Runtime> Js<R> {
             }
         };
 
+        Ok((program, args_vec))
+    }
+
+    /// Command for `config` with its working directory, environment, process group and
+    /// parent-death signal set; stdio is left to the caller. Also returns the command line,
+    /// program first.
+    async fn command(&self, config: &SpawnConfig) -> crate::Result<(Command, Vec<String>)> {
+        let (program, args_vec) = self.resolve_program(config).await?;
+
         let mut cmd = Command::new(&program);
         cmd.args(&args_vec);
-        cmd.stdin(std::process::Stdio::piped());
-        cmd.stdout(std::process::Stdio::piped());
-        cmd.stderr(std::process::Stdio::piped());
-
         if let Some(ref cwd) = config.cwd {
             cmd.current_dir(cwd);
         }
@@ -113,108 +942,158 @@ impl<R: Runtime> Js<R> {
                 cmd.env(k, v);
             }
         }
-
-        let mut child = cmd.spawn().map_err(crate::Error::Io)?;
-
-        let pid = child.id();
-        let stdout = child.stdout.take();
-        let stderr = child.stderr.take();
-        let stdin = child.stdin.take();
-
-        let entry = ProcessEntry {
-            child,
-            stdin,
-            config: config.clone(),
-        };
-
-        {
-            let mut procs = self.processes.lock().await;
-            procs.insert(name.clone(), entry);
+        // A pty's session is a process group of its own already
+        #[cfg(unix)]
+        if config.process_group.unwrap_or(false) && !config.pty.unwrap_or(false) {
+            cmd.process_group(0);
         }
-
-        // Spawn stdout reader task
-        if let Some(stdout) = stdout {
-            let app = self.app.clone();
-            let proc_name = name.clone();
-            tauri::async_runtime::spawn(async move {
-                let reader = BufReader::new(stdout);
-                let mut lines = reader.lines();
-                while let Ok(Some(line)) = lines.next_line().await {
-                    let payload = StdioEventPayload {
-                        name: proc_name.clone(),
-                        data: line,
-                    };
-                    let _ = app.emit("js-process-stdout", &payload);
-                }
-            });
+        #[cfg(target_os = "linux")]
+        if config.kill_on_parent_death.unwrap_or(false) {
+            kill_on_parent_death(&mut cmd);
         }
 
-        // Spawn stderr reader task
-        if let Some(stderr) = stderr {
-            let app = self.app.clone();
-            let proc_name = name.clone();
-            tauri::async_runtime::spawn(async move {
-                let reader = BufReader::new(stderr);
-                let mut lines = reader.lines();
-                while let Ok(Some(line)) = lines.next_line().await {
-                    let payload = StdioEventPayload {
-                        name: proc_name.clone(),
-                        data: line,
-                    };
-                    let _ = app.emit("js-process-stderr", &payload);
+        let command_line = std::iter::once(program).chain(args_vec).collect();
+        Ok((cmd, command_line))
+    }
+
+    /// Socket path for an instance, in a directory private to this app. Sockets live in the
+    /// runtime dir where there is one (Linux), otherwise in the temp dir.
+    fn socket_path(&self, instance_id: u64) -> crate::Result<std::path::PathBuf> {
+        let path = self.app.path();
+        let base = path
+            .runtime_dir()
+            .or_else(|_| path.temp_dir())
+            .map_err(|e| {
+                crate::Error::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("could not determine socket directory: {e}"),
+                ))
+            })?;
+        // Include our pid, as instance ids repeat across runs and app instances
+        Ok(base
+            .join(format!("{}-js", self.app.config().identifier))
+            .join(format!("{}-{}.sock", std::process::id(), instance_id)))
+    }
+
+    /// File an instance's over-limit output is spilled to, in the app's log directory or,
+    /// failing that, next to its sockets in the temp dir.
+    fn spill_path(&self, name: &str, instance_id: u64) -> crate::Result<std::path::PathBuf> {
+        let path = self.app.path();
+        let dir = path
+            .app_log_dir()
+            .map(|dir| dir.join("js"))
+            .or_else(|_| {
+                path.temp_dir()
+                    .map(|dir| dir.join(format!("{}-js", self.app.config().identifier)))
+            })
+            .map_err(|e| {
+                crate::Error::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("could not determine spill directory: {e}"),
+                ))
+            })?;
+        // Process names are arbitrary strings, keep them from escaping the directory
+        let file_name: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
                 }
-            });
-        }
+            })
+            .collect();
+        Ok(dir.join(format!("{}-{}.log", file_name, instance_id)))
+    }
 
-        // Spawn exit watcher task
-        {
-            let app = self.app.clone();
-            let proc_name = name.clone();
-            let processes = self.processes.clone();
-            tauri::async_runtime::spawn(async move {
-                // Wait for the child to exit by polling its status
-                loop {
-                    let exit_status = {
-                        let mut procs = processes.lock().await;
-                        if let Some(entry) = procs.get_mut(&proc_name) {
-                            match entry.child.try_wait() {
-                                Ok(Some(status)) => Some(status.code()),
-                                Ok(None) => None,
-                                Err(_) => {
-                                    // Process errored, treat as exited
-                                    Some(None)
-                                }
-                            }
-                        } else {
-                            // Entry was removed (killed), stop watching
+    /// Accepts connections to a process's socket until it is closed, forwarding each
+    /// connection's messages as `js-process-ipc` events.
+    fn spawn_socket_listener(
+        &self,
+        source: OutputSource,
+        socket: IpcSocket,
+        mut closed: watch::Receiver<()>,
+        framing: Framing,
+    ) {
+        let app = self.app.clone();
+        let processes = self.processes.clone();
+        let name = source.name.clone();
+        let instance_id = source.instance_id;
+        tauri::async_runtime::spawn(async move {
+            let mut next_connection_id = 1;
+            loop {
+                let (reader, writer) = tokio::select! {
+                    accepted = socket.accept() => match accepted {
+                        Ok(connection) => connection,
+                        Err(e) => {
+                            log::warn!("stopped accepting connections for '{}': {}", name, e);
                             break;
                         }
-                    };
+                    },
+                    _ = closed.changed() => break,
+                };
+                let connection_id = next_connection_id;
+                next_connection_id += 1;
 
-                    if let Some(code) = exit_status {
-                        // Remove from map
-                        {
-                            let mut procs = processes.lock().await;
-                            procs.remove(&proc_name);
+                {
+                    let mut procs = processes.lock().await;
+                    match procs
+                        .get_mut(&name)
+                        .filter(|entry| entry.info.instance_id == instance_id)
+                    {
+                        Some(entry) if entry.socket_guard.is_some() => {
+                            let writer =
+                                WriteQueue::spawn(writer, entry.info.config.write_queue.as_ref());
+                            entry.connections.insert(connection_id, writer);
                         }
-                        let payload = ExitEventPayload {
-                            name: proc_name,
-                            code,
-                        };
-                        let _ = app.emit("js-process-exit", &payload);
-                        break;
+                        _ => break,
                     }
-
-                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
                 }
-            });
-        }
+                let mut payload = ConnectionEventPayload {
+                    name: name.clone(),
+                    instance_id,
+                    connection_id,
+                    connected: true,
+                };
+                publish(
+                    &app,
+                    source.broadcast,
+                    ProcessEvent::Connection(payload.clone()),
+                );
 
-        Ok(ProcessInfo {
-            name,
-            pid,
-            running: true,
-        })
+                let app = app.clone();
+                let processes = processes.clone();
+                let mut closed = closed.clone();
+                let source = OutputSource {
+                    connection_id: Some(connection_id),
+                    ..source.clone()
+                };
+                let broadcast = source.broadcast;
+                tauri::async_runtime::spawn(async move {
+                    tokio::select! {
+                        _ = forward_output(
+                            app.clone(),
+                            source,
+                            reader,
+                            framing,
+                            OutputEncoding::Lossy,
+                        ) => {}
+                        _ = closed.changed() => {}
+                    }
+                    if let Some(entry) = processes
+                        .lock()
+                        .await
+                        .get_mut(&payload.name)
+                        .filter(|entry| entry.info.instance_id == instance_id)
+                    {
+                        entry.connections.remove(&connection_id);
+                    }
+                    payload.connected = false;
+                    publish(&app, broadcast, ProcessEvent::Connection(payload));
+                });
+            }
+            // Dropping the socket removes it from disk
+        });
     }
 
     fn resolve_sidecar(&self, name: &str) -> crate::Result<std::path::PathBuf> {
@@ -261,98 +1140,466 @@ impl<R: Runtime> Js<R> {
         )))
     }
 
-    pub async fn kill(&self, name: String) -> crate::Result<()> {
-        let mut entry = {
+    pub async fn kill(
+        &self,
+        name: String,
+        instance_id: Option<u64>,
+    ) -> crate::Result<ShutdownResult> {
+        self.stop(name, instance_id, ExitReason::KilledByRequest)
+            .await
+    }
+
+    async fn stop(
+        &self,
+        name: String,
+        instance_id: Option<u64>,
+        reason: ExitReason,
+    ) -> crate::Result<ShutdownResult> {
+        let handle = {
             let mut procs = self.processes.lock().await;
-            procs
-                .remove(&name)
-                .ok_or_else(|| crate::Error::ProcessNotFound(name.clone()))?
+            match procs.get_mut(&name) {
+                Some(entry) if entry.matches(instance_id) => {
+                    match entry.begin_stop(&name, reason) {
+                        Some(handle) => handle,
+                        None => return Ok(entry.already_exited(name)),
+                    }
+                }
+                _ => match instance_id {
+                    // The requested instance is gone; killing it again is not an error
+                    Some(instance_id) => {
+                        return Ok(ShutdownResult {
+                            name,
+                            instance_id,
+                            outcome: ShutdownOutcome::AlreadyExited,
+                            code: None,
+                            signal: None,
+                            elapsed_ms: 0,
+                        })
+                    }
+                    None => return Err(crate::Error::ProcessNotFound(name)),
+                },
+            }
         };
 
-        // Drop stdin first to signal EOF
-        entry.stdin.take();
-        // Kill the child outside the lock
-        let _ = entry.child.kill().await;
-        Ok(())
+        // Stop the child outside the lock
+        Ok(shutdown(handle).await)
     }
 
-    pub async fn kill_all(&self) -> crate::Result<()> {
-        let entries: Vec<(String, ProcessEntry)> = {
+    pub async fn kill_all(&self) -> crate::Result<Vec<ShutdownResult>> {
+        let handles: Vec<StopHandle> = {
             let mut procs = self.processes.lock().await;
-            procs.drain().collect()
+            procs
+                .iter_mut()
+                .filter_map(|(name, entry)| entry.begin_stop(name, ExitReason::KilledByRequest))
+                .collect()
         };
 
-        for (_, mut entry) in entries {
-            entry.stdin.take();
-            let _ = entry.child.kill().await;
+        // Stop all processes concurrently so their grace periods overlap
+        let tasks: Vec<_> = handles
+            .into_iter()
+            .map(|handle| tauri::async_runtime::spawn(shutdown(handle)))
+            .collect();
+
+        let mut results = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            if let Ok(result) = task.await {
+                results.push(result);
+            }
         }
-        Ok(())
+        Ok(results)
     }
 
+    /// Stops the process and spawns it again with `config`, or with the config it was last
+    /// spawned with. A process reattached from a previous run needs `config`, as only its
+    /// command line was recorded.
     pub async fn restart(
         &self,
         name: String,
         config: Option<SpawnConfig>,
+        instance_id: Option<u64>,
     ) -> crate::Result<ProcessInfo> {
         // Get the old config before killing
-        let old_config = {
+        let (old_config, current_id, reattached) = {
             let procs = self.processes.lock().await;
-            procs
-                .get(&name)
-                .map(|e| e.config.clone())
-                .ok_or_else(|| crate::Error::ProcessNotFound(name.clone()))?
+            let entry = Self::instance(&procs, &name, instance_id)?;
+            (
+                entry.info.config.clone(),
+                entry.info.instance_id,
+                entry.orphan_start_time.is_some(),
+            )
+        };
+        let spawn_config = match config {
+            Some(config) => config,
+            // Its env, runtime and framing were lost with the run that spawned it
+            None if reattached => {
+                return Err(crate::Error::InvalidConfig(format!(
+                    "process '{}' was reattached from a previous run, pass a config to restart it",
+                    name
+                )))
+            }
+            None => old_config,
         };
 
-        self.kill(name.clone()).await?;
-        let spawn_config = config.unwrap_or(old_config);
-        self.spawn(name, spawn_config).await
+        // Stopping an exited process is a no-op, so exited processes can be restarted too
+        let result = self
+            .stop(name.clone(), Some(current_id), ExitReason::Restart)
+            .await?;
+        log::debug!(
+            "restart: '{}' stopped ({:?}) after {}ms",
+            name,
+            result.outcome,
+            result.elapsed_ms
+        );
+        let result = self.spawn(name.clone(), spawn_config).await;
+        if result.is_err() {
+            // The old instance was left waiting to be restarted
+            self.mark_failed(&name, current_id).await;
+        }
+        result
     }
 
+    /// Lists live processes along with recently exited ones.
     pub async fn list_processes(&self) -> crate::Result<Vec<ProcessInfo>> {
         let procs = self.processes.lock().await;
-        let mut list = Vec::new();
-        for (name, entry) in procs.iter() {
-            list.push(ProcessInfo {
-                name: name.clone(),
-                pid: entry.child.id(),
-                running: true,
-            });
+        let mut infos = Vec::with_capacity(procs.len());
+        for entry in procs.values() {
+            infos.push(entry.snapshot());
         }
-        Ok(list)
+        Ok(infos)
     }
 
-    pub async fn get_status(&self, name: String) -> crate::Result<ProcessInfo> {
+    /// Processes left running by previous runs of the app and what was done with them, see
+    /// [`OrphanPolicy`].
+    pub async fn list_orphans(&self) -> crate::Result<Vec<OrphanInfo>> {
+        Ok(self.orphans.clone())
+    }
+
+    pub async fn get_status(
+        &self,
+        name: String,
+        instance_id: Option<u64>,
+    ) -> crate::Result<ProcessInfo> {
         let procs = self.processes.lock().await;
-        let entry = procs
-            .get(&name)
-            .ok_or_else(|| crate::Error::ProcessNotFound(name.clone()))?;
-        Ok(ProcessInfo {
-            name,
-            pid: entry.child.id(),
-            running: true,
-        })
+        let entry = Self::instance(&procs, &name, instance_id)?;
+        Ok(entry.snapshot())
     }
 
-    pub async fn write_stdin(&self, name: String, data: String) -> crate::Result<()> {
-        let mut procs = self.processes.lock().await;
-        let entry = procs
-            .get_mut(&name)
-            .ok_or_else(|| crate::Error::ProcessNotFound(name.clone()))?;
-        let stdin = entry
-            .stdin
-            .as_mut()
-            .ok_or_else(|| crate::Error::ProcessNotRunning(name.clone()))?;
-        stdin
-            .write_all(data.as_bytes())
+    /// Waits for an instance of `name` to exit, by default the current one, and returns how it
+    /// exited; right away if it already has, including for one of the last
+    /// [`MAX_REPLACED_INSTANCES`] instances the name has since been spawned over. Fails with
+    /// [`crate::Error::WaitTimeout`] once `timeout_ms` has passed, leaving the process running.
+    pub async fn wait(
+        &self,
+        name: String,
+        timeout_ms: Option<u64>,
+        instance_id: Option<u64>,
+    ) -> crate::Result<ExitEventPayload> {
+        let mut exit_rx = {
+            let procs = self.processes.lock().await;
+            let replaced = instance_id.zip(procs.get(&name)).and_then(|(id, entry)| {
+                entry.replaced.iter().find(|(replaced, _)| *replaced == id)
+            });
+            match replaced {
+                Some((_, exit_rx)) => exit_rx.clone(),
+                None => Self::instance(&procs, &name, instance_id)?.exit_rx.clone(),
+            }
+        };
+        let exited = exit_rx.wait_for(|exited| exited.is_some());
+        let exited = match timeout_ms {
+            Some(ms) => tokio::time::timeout(Duration::from_millis(ms), exited)
+                .await
+                .map_err(|_| crate::Error::WaitTimeout(name.clone()))?,
+            None => exited.await,
+        };
+        // The exit watcher only goes away without publishing the exit if the app is shutting
+        // down
+        let event = exited
+            .ok()
+            .and_then(|exited| exited.as_ref().map(|exited| exited.event.clone()));
+        event.ok_or(crate::Error::ProcessNotRunning(name))
+    }
+
+    /// Waits until a line on a stream of an instance of `name`, by default stdout of the
+    /// current instance, contains `pattern`, or matches it as a regular expression with
+    /// `options.regex`, and returns the line. Output still in the replay buffer counts, so a
+    /// line printed before the call matches right away. Fails with
+    /// [`crate::Error::ExitedBeforeMatch`] if the instance exits first, and with
+    /// [`crate::Error::WaitTimeout`] once `options.timeout_ms` has passed.
+    pub async fn wait_for_output(
+        &self,
+        name: String,
+        pattern: String,
+        options: WaitForOutputOptions,
+    ) -> crate::Result<OutputMatch> {
+        let pattern = OutputPattern::new(&pattern, options.regex.unwrap_or(false))?;
+        let stream = options.stream.unwrap_or(StdioStream::Stdout);
+        let (instance_id, mut exit_rx, (subscription_id, mut rx)) = {
+            let procs = self.processes.lock().await;
+            let entry = Self::instance(&procs, &name, options.instance_id)?;
+            (
+                entry.info.instance_id,
+                entry.exit_rx.clone(),
+                self.subscriptions.listen(name.clone()),
+            )
+        };
+        let found = find_output(&name, &pattern, instance_id, stream, &mut rx, &mut exit_rx);
+        let result = match options.timeout_ms {
+            Some(ms) => tokio::time::timeout(Duration::from_millis(ms), found)
+                .await
+                .unwrap_or_else(|_| Err(crate::Error::WaitTimeout(name.clone()))),
+            None => found.await,
+        };
+        self.subscriptions.remove(subscription_id);
+        result
+    }
+
+    /// Streams the stdout, stderr, IPC, readiness and exit events of every instance of `name`
+    /// to `channel` until [`Js::unsubscribe`] is called or the webview goes away. `webview`
+    /// and `window` are the labels of the webview that owns the channel and of its window.
+    /// With `from_seq`, buffered output from that sequence number on is replayed first; 0
+    /// replays everything still buffered.
+    pub async fn subscribe(
+        &self,
+        name: String,
+        webview: String,
+        window: String,
+        channel: Channel<ProcessEvent>,
+        from_seq: Option<u64>,
+    ) -> crate::Result<u64> {
+        Ok(self
+            .subscriptions
+            .add(name, webview, window, channel, from_seq))
+    }
+
+    pub async fn unsubscribe(&self, subscription_id: u64) -> crate::Result<()> {
+        self.subscriptions.remove(subscription_id);
+        Ok(())
+    }
+
+    /// Drops the subscriptions of a webview that navigated away or was closed.
+    pub fn unsubscribe_webview(&self, webview: &str) {
+        self.subscriptions.remove_webview(webview);
+    }
+
+    /// Drops the subscriptions of the webviews of a destroyed window.
+    pub fn unsubscribe_window(&self, window: &str) {
+        self.subscriptions.remove_window(window);
+    }
+
+    /// Queues `data` for the process's stdin and resolves once it has been written. The
+    /// process map is not locked while waiting, so a child that stops reading stalls only
+    /// its own writes.
+    pub async fn write_stdin(
+        &self,
+        name: String,
+        data: String,
+        instance_id: Option<u64>,
+    ) -> crate::Result<()> {
+        self.write_stdin_bytes(name, data.into_bytes(), instance_id)
             .await
+    }
+
+    /// Like [`Js::write_stdin`], for data that is not UTF-8.
+    pub async fn write_stdin_bytes(
+        &self,
+        name: String,
+        data: Vec<u8>,
+        instance_id: Option<u64>,
+    ) -> crate::Result<()> {
+        let (stdin, framing) = {
+            let procs = self.processes.lock().await;
+            let entry = Self::instance(&procs, &name, instance_id)?;
+            let stdin = match &entry.stdin {
+                Some(stdin) => stdin.clone(),
+                None if entry.info.state.is_alive() => {
+                    return Err(crate::Error::StdinClosed(name));
+                }
+                None => return Err(crate::Error::ProcessNotRunning(name)),
+            };
+            (stdin, stdio_framing(&entry.info.config))
+        };
+        let frame = framing::encode_frame(framing, &data)
             .map_err(|e| crate::Error::StdinWriteError(name.clone(), e.to_string()))?;
         stdin
-            .flush()
+            .write(frame)
             .await
-            .map_err(|e| crate::Error::StdinWriteError(name, e.to_string()))?;
+            .map_err(|e| write_error(name, e, crate::Error::StdinWriteError))
+    }
+
+    /// Closes the process's stdin once the writes already queued have gone out, so it sees
+    /// EOF, while leaving it running. Closing it again is a no-op. A pty has no stdin of its
+    /// own to close; the process sees EOF when `"\x04"` is written instead.
+    pub async fn close_stdin(&self, name: String, instance_id: Option<u64>) -> crate::Result<()> {
+        let mut procs = self.processes.lock().await;
+        let entry = Self::instance_mut(&mut procs, &name, instance_id)?;
+        if !entry.info.state.is_alive() {
+            return Err(crate::Error::ProcessNotRunning(name));
+        }
+        if entry.pty.is_some() {
+            return Err(crate::Error::InvalidConfig(format!(
+                "process '{}' runs in a pty, write \"\\x04\" to send EOF instead",
+                name
+            )));
+        }
+        entry.stdin = None;
         Ok(())
     }
 
+    /// Sends `signal` to a live process, e.g. SIGHUP to reload its config. SIGSTOP moves it to
+    /// `Suspended` and SIGCONT back to the state it was in. Unix only.
+    pub async fn signal(
+        &self,
+        name: String,
+        signal: String,
+        instance_id: Option<u64>,
+    ) -> crate::Result<()> {
+        if !SIGNALS.contains(&signal.as_str()) {
+            return Err(crate::Error::InvalidConfig(format!(
+                "unsupported signal: {}",
+                signal
+            )));
+        }
+        let mut procs = self.processes.lock().await;
+        let entry = Self::instance_mut(&mut procs, &name, instance_id)?;
+        let pid = entry
+            .info
+            .pid
+            .filter(|&pid| {
+                entry.info.state.is_alive()
+                    && !*entry.reaped_rx.borrow()
+                    && is_same_process(pid, entry.orphan_start_time.as_deref())
+            })
+            .ok_or_else(|| crate::Error::ProcessNotRunning(name.clone()))?;
+        send_signal(pid, &signal).map_err(crate::Error::Io)?;
+
+        match (signal.as_str(), entry.info.state) {
+            ("SIGSTOP", ProcessState::Suspended | ProcessState::Stopping) => {}
+            ("SIGSTOP", state) => {
+                entry.resume_state = Some(state);
+                entry.set_state(ProcessState::Suspended);
+            }
+            ("SIGCONT", ProcessState::Suspended) => {
+                let state = entry.resume_state.take().unwrap_or(ProcessState::Running);
+                entry.set_state(state);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Resizes the pseudo-terminal of a process spawned with `pty`, which is sent SIGWINCH.
+    pub async fn resize(
+        &self,
+        name: String,
+        cols: u16,
+        rows: u16,
+        instance_id: Option<u64>,
+    ) -> crate::Result<()> {
+        if cols == 0 || rows == 0 {
+            return Err(crate::Error::InvalidConfig(
+                "'cols' and 'rows' must be at least 1".to_string(),
+            ));
+        }
+        let procs = self.processes.lock().await;
+        let entry = Self::instance(&procs, &name, instance_id)?;
+        if !entry.info.config.pty.unwrap_or(false) {
+            return Err(crate::Error::InvalidConfig(format!(
+                "process '{}' was not spawned with pty",
+                name
+            )));
+        }
+        let pty = entry
+            .pty
+            .as_ref()
+            .ok_or_else(|| crate::Error::ProcessNotRunning(name.clone()))?;
+        pty.resize(PtySize { cols, rows }).map_err(crate::Error::Io)
+    }
+
+    /// Sends a message to a process spawned with `ipc` or `socket`. Socket messages go to
+    /// `connection_id`, or to every open connection if it is `None`.
+    pub async fn write_ipc(
+        &self,
+        name: String,
+        data: String,
+        instance_id: Option<u64>,
+        connection_id: Option<u64>,
+    ) -> crate::Result<()> {
+        let (writers, framing) = {
+            let procs = self.processes.lock().await;
+            let entry = Self::instance(&procs, &name, instance_id)?;
+            let config = &entry.info.config;
+            let writers: Vec<WriteQueue> = if config.socket.unwrap_or(false) {
+                match connection_id {
+                    Some(id) => entry.connections.get(&id).cloned().into_iter().collect(),
+                    None => entry.connections.values().cloned().collect(),
+                }
+            } else if config.ipc.unwrap_or(false) {
+                entry.ipc.clone().into_iter().collect()
+            } else {
+                return Err(crate::Error::InvalidConfig(format!(
+                    "process '{}' was not spawned with ipc or socket",
+                    name
+                )));
+            };
+            (writers, config.framing.unwrap_or_default())
+        };
+        if writers.is_empty() {
+            return Err(crate::Error::IpcWriteError(
+                name,
+                match connection_id {
+                    Some(id) => format!("connection {} is not open", id),
+                    None => "no open connection".to_string(),
+                },
+            ));
+        }
+
+        let frame = framing::encode_frame(framing, data.as_bytes())
+            .map_err(|e| crate::Error::IpcWriteError(name.clone(), e.to_string()))?;
+        // Queue on every connection before waiting, so one slow reader doesn't hold up the rest
+        let mut pending = Vec::with_capacity(writers.len());
+        for writer in &writers {
+            match writer.enqueue(frame.clone()).await {
+                Ok(written) => pending.push(written),
+                Err(e) => return Err(write_error(name, e, crate::Error::IpcWriteError)),
+            }
+        }
+        for written in pending {
+            if let Err(e) = written.wait().await {
+                return Err(write_error(name, e, crate::Error::IpcWriteError));
+            }
+        }
+        Ok(())
+    }
+
+    /// Looks up the entry for `name`, failing if it is not the requested instance.
+    fn instance<'a>(
+        procs: &'a HashMap<String, ProcessEntry>,
+        name: &str,
+        instance_id: Option<u64>,
+    ) -> crate::Result<&'a ProcessEntry> {
+        match procs.get(name) {
+            Some(entry) if entry.matches(instance_id) => Ok(entry),
+            Some(_) | None => Err(match instance_id {
+                Some(id) => crate::Error::InstanceNotRunning(name.to_string(), id),
+                None => crate::Error::ProcessNotFound(name.to_string()),
+            }),
+        }
+    }
+
+    fn instance_mut<'a>(
+        procs: &'a mut HashMap<String, ProcessEntry>,
+        name: &str,
+        instance_id: Option<u64>,
+    ) -> crate::Result<&'a mut ProcessEntry> {
+        match procs.get_mut(name) {
+            Some(entry) if entry.matches(instance_id) => Ok(entry),
+            Some(_) | None => Err(match instance_id {
+                Some(id) => crate::Error::InstanceNotRunning(name.to_string(), id),
+                None => crate::Error::ProcessNotFound(name.to_string()),
+            }),
+        }
+    }
+
     pub async fn detect_runtimes(&self) -> crate::Result<Vec<RuntimeInfo>> {
         let runtimes = ["bun", "node", "deno"];
         let mut results = Vec::new();
@@ -415,3 +1662,1092 @@ impl<R: Runtime> Js<R> {
         Ok(paths.clone())
     }
 }
+
+/// Where forwarded output comes from and whether it is broadcast, see [`publish`].
+#[derive(Clone)]
+struct OutputSource {
+    name: String,
+    instance_id: u64,
+    stream: StdioStream,
+    /// Socket connection the output arrives on, if any
+    connection_id: Option<u64>,
+    broadcast: bool,
+    /// Set for stdout and stderr when the process batches its output
+    batch: Option<BatchConfig>,
+    limiter: Option<Arc<OutputLimiter>>,
+}
+
+impl OutputSource {
+    fn new(name: &str, instance_id: u64, stream: StdioStream, config: &SpawnConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            instance_id,
+            stream,
+            connection_id: None,
+            broadcast: config.broadcast.unwrap_or(true),
+            // IPC messages are RPC traffic, which should not wait for a batch to fill up
+            batch: config.batch.clone().filter(|_| stream != StdioStream::Ipc),
+            limiter: None,
+        }
+    }
+}
+
+/// Output of a one-shot run, kept up to a size cap.
+struct OutputCapture {
+    data: Vec<u8>,
+    max_bytes: usize,
+    truncated: bool,
+}
+
+impl OutputCapture {
+    fn new(max_bytes: usize) -> Self {
+        Self {
+            data: Vec::new(),
+            max_bytes,
+            truncated: false,
+        }
+    }
+
+    /// Reads `output` to the end, keeping what fits. Output over the cap is still read, so
+    /// the process never blocks on a full pipe.
+    async fn read_from(&mut self, output: Option<impl AsyncRead + Unpin>) {
+        let Some(mut output) = output else {
+            return;
+        };
+        let mut buf = vec![0; 8192];
+        loop {
+            let n = match output.read(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            let room = self.max_bytes.saturating_sub(self.data.len());
+            if n > room {
+                self.truncated = true;
+            }
+            self.data.extend_from_slice(&buf[..n.min(room)]);
+        }
+    }
+
+    fn finish(mut self, encoding: OutputEncoding) -> String {
+        if encoding == OutputEncoding::Base64 {
+            return BASE64.encode(&self.data);
+        }
+        // Don't end in a replacement character where the cap cut a character in two
+        if self.truncated {
+            framing::split_incomplete_utf8(&mut self.data);
+        }
+        String::from_utf8_lossy(&self.data).into_owned()
+    }
+}
+
+/// Forwards a child's stdout, stderr or IPC pipe in a background task, see [`forward_output`].
+/// The task ends once the output has been read to the end and published.
+fn spawn_output_reader<R: Runtime>(
+    app: AppHandle<R>,
+    source: OutputSource,
+    output: impl AsyncRead + Unpin + Send + 'static,
+    framing: Framing,
+    encoding: OutputEncoding,
+) -> tauri::async_runtime::JoinHandle<()> {
+    tauri::async_runtime::spawn(forward_output(app, source, output, framing, encoding))
+}
+
+/// Reads events from `rx` until output of the instance matches `pattern`, or the instance
+/// exits. The exit is only recorded once its output has been published, so no output of the
+/// instance can arrive after it.
+async fn find_output(
+    name: &str,
+    pattern: &OutputPattern,
+    instance_id: u64,
+    stream: StdioStream,
+    rx: &mut mpsc::UnboundedReceiver<ProcessEvent>,
+    exit_rx: &mut watch::Receiver<Option<Exited>>,
+) -> crate::Result<OutputMatch> {
+    loop {
+        let event = tokio::select! {
+            // Drain what has already been published before looking at the exit
+            biased;
+            Some(event) = rx.recv() => event,
+            _ = exit_rx.wait_for(|exited| exited.is_some()) => {
+                return Err(crate::Error::ExitedBeforeMatch(name.to_string()));
+            }
+        };
+        if let Some(found) = pattern.find(&event, instance_id, stream) {
+            return Ok(found);
+        }
+    }
+}
+
+/// A readiness probe and the instance it checks.
+struct ReadinessProbe {
+    name: String,
+    instance_id: u64,
+    probe: Probe,
+    config: ReadinessConfig,
+    broadcast: bool,
+}
+
+/// Runs an instance's readiness probe until it succeeds, times out or the instance exits.
+/// A ready instance moves from [`ProcessState::Starting`] to [`ProcessState::Ready`] and
+/// `js-process-ready` is emitted; otherwise `js-process-readiness-failed` is, and the
+/// instance is left running as [`ProcessState::Running`].
+async fn watch_readiness<R: Runtime>(
+    app: AppHandle<R>,
+    probe: ReadinessProbe,
+    mut exit_rx: watch::Receiver<Option<Exited>>,
+    ready_tx: watch::Sender<Option<ReadinessEventPayload>>,
+) {
+    let started = Instant::now();
+    let timeout_ms = probe
+        .config
+        .timeout_ms
+        .unwrap_or(DEFAULT_READINESS_TIMEOUT_MS);
+    let result = tokio::time::timeout(
+        Duration::from_millis(timeout_ms),
+        run_probe(&app, &probe, &mut exit_rx),
+    )
+    .await
+    .unwrap_or_else(|_| Err(format!("not ready after {}ms", timeout_ms)));
+
+    let payload = ReadinessEventPayload {
+        name: probe.name.clone(),
+        instance_id: probe.instance_id,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        error: result.err(),
+    };
+    let settled = if payload.error.is_none() {
+        ProcessState::Ready
+    } else {
+        ProcessState::Running
+    };
+    {
+        let mut procs = app.js().processes.lock().await;
+        if let Some(entry) = procs
+            .get_mut(&probe.name)
+            .filter(|entry| entry.info.instance_id == probe.instance_id)
+        {
+            match entry.info.state {
+                ProcessState::Starting | ProcessState::Running => entry.set_state(settled),
+                ProcessState::Suspended
+                    if matches!(
+                        entry.resume_state,
+                        Some(ProcessState::Starting | ProcessState::Running)
+                    ) =>
+                {
+                    entry.resume_state = Some(settled)
+                }
+                _ => {}
+            }
+        }
+    }
+    let event = if payload.error.is_none() {
+        ProcessEvent::Ready(payload.clone())
+    } else {
+        ProcessEvent::ReadinessFailed(payload.clone())
+    };
+    publish(&app, probe.broadcast, event);
+    let _ = ready_tx.send(Some(payload));
+}
+
+/// Checks readiness every `interval_ms` until the probe succeeds. Fails if the instance
+/// exits first, or a ping cannot be written.
+async fn run_probe<R: Runtime>(
+    app: &AppHandle<R>,
+    probe: &ReadinessProbe,
+    exit_rx: &mut watch::Receiver<Option<Exited>>,
+) -> std::result::Result<(), String> {
+    const EXITED: &str = "exited before it was ready";
+    let interval = Duration::from_millis(
+        probe
+            .config
+            .interval_ms
+            .unwrap_or(DEFAULT_PROBE_INTERVAL_MS),
+    );
+    let (name, instance_id) = (&probe.name, probe.instance_id);
+    match &probe.probe {
+        Probe::Output(pattern) => {
+            let (subscription_id, mut rx) = app.js().subscriptions.listen(name.clone());
+            let found = find_output(
+                name,
+                pattern,
+                instance_id,
+                StdioStream::Stdout,
+                &mut rx,
+                exit_rx,
+            )
+            .await;
+            app.js().subscriptions.remove(subscription_id);
+            found.map(drop).map_err(|_| EXITED.to_string())
+        }
+        Probe::Tcp(port) => poll_ready(interval, exit_rx, || readiness::tcp_ready(*port))
+            .await
+            .then_some(())
+            .ok_or_else(|| EXITED.to_string()),
+        Probe::Http(target) => poll_ready(interval, exit_rx, || readiness::http_ready(target))
+            .await
+            .then_some(())
+            .ok_or_else(|| EXITED.to_string()),
+        Probe::Ping { message, response } => {
+            let (subscription_id, mut rx) = app.js().subscriptions.listen(name.clone());
+            let result = loop {
+                if let Err(e) = app
+                    .js()
+                    .write_stdin(name.clone(), message.clone(), Some(instance_id))
+                    .await
+                {
+                    break Err(if exit_rx.borrow().is_some() {
+                        EXITED.to_string()
+                    } else {
+                        format!("could not write the ping: {}", e)
+                    });
+                }
+                // An answer to an earlier ping still counts
+                let found = find_output(
+                    name,
+                    response,
+                    instance_id,
+                    StdioStream::Stdout,
+                    &mut rx,
+                    exit_rx,
+                );
+                match tokio::time::timeout(interval, found).await {
+                    Ok(Ok(_)) => break Ok(()),
+                    Ok(Err(_)) => break Err(EXITED.to_string()),
+                    Err(_) => {}
+                }
+            };
+            app.js().subscriptions.remove(subscription_id);
+            result
+        }
+    }
+}
+
+/// Runs `check` every `interval` until it succeeds. An attempt that outlasts the interval,
+/// kept within [`MIN_PROBE_ATTEMPT_TIMEOUT`] and [`MAX_PROBE_ATTEMPT_TIMEOUT`], counts as
+/// failed, so a server that accepts a connection but never answers does not stall the
+/// probe. Returns `false` if the instance exits first.
+async fn poll_ready<F, Fut>(
+    interval: Duration,
+    exit_rx: &mut watch::Receiver<Option<Exited>>,
+    mut check: F,
+) -> bool
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let attempt_timeout = interval.clamp(MIN_PROBE_ATTEMPT_TIMEOUT, MAX_PROBE_ATTEMPT_TIMEOUT);
+    let attempts = async {
+        while !tokio::time::timeout(attempt_timeout, check())
+            .await
+            .unwrap_or(false)
+        {
+            tokio::time::sleep(interval).await;
+        }
+    };
+    tokio::select! {
+        _ = attempts => true,
+        _ = exit_rx.wait_for(|exited| exited.is_some()) => false,
+    }
+}
+
+/// Publishes output as stdout, stderr or IPC events, one per frame, decoding each frame
+/// according to `encoding`, until the stream ends. Frames are split on raw bytes, so output
+/// that is not valid UTF-8 never stops the reader. Frames over the rate limit are held back,
+/// and with batching, frames are handed to [`batch_output`] instead.
+async fn forward_output<R: Runtime>(
+    app: AppHandle<R>,
+    source: OutputSource,
+    output: impl AsyncRead + Unpin,
+    framing: Framing,
+    encoding: OutputEncoding,
+) {
+    let batcher = source.batch.clone().map(|batch| {
+        let (lines_tx, lines_rx) = mpsc::channel(BATCH_QUEUE_LEN);
+        let task =
+            tauri::async_runtime::spawn(batch_output(app.clone(), source.clone(), batch, lines_rx));
+        (lines_tx, task)
+    });
+    let mut reader = BufReader::new(output);
+    let mut buf = Vec::new();
+    // Start of a UTF-8 sequence cut off at the end of the previous raw chunk
+    let mut carry = Vec::new();
+    let mut ended = false;
+    while !ended {
+        ended = match framing::read_frame(&mut reader, framing, &mut buf).await {
+            Ok(more) => !more,
+            // An oversized frame was skipped, the next one can still be read
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+                let payload = DecodeErrorEventPayload {
+                    name: source.name.clone(),
+                    instance_id: source.instance_id,
+                    stream: source.stream,
+                    error: e.to_string(),
+                    data: String::new(),
+                };
+                publish(&app, source.broadcast, ProcessEvent::DecodeError(payload));
+                continue;
+            }
+            Err(e) => {
+                log::debug!(
+                    "stopped reading {:?} of '{}': {}",
+                    source.stream,
+                    source.name,
+                    e
+                );
+                true
+            }
+        };
+        let captured_at = now_ms();
+        if ended {
+            // A sequence still cut off at the end never completes, so it is decoded as is
+            if carry.is_empty() {
+                break;
+            }
+            buf = std::mem::take(&mut carry);
+        } else if framing == Framing::Raw && encoding != OutputEncoding::Base64 {
+            buf.splice(0..0, carry.drain(..));
+            carry = framing::split_incomplete_utf8(&mut buf);
+            if buf.is_empty() {
+                continue;
+            }
+        }
+
+        let data = match encoding {
+            OutputEncoding::Lossy => String::from_utf8_lossy(&buf).into_owned(),
+            OutputEncoding::Base64 => BASE64.encode(&buf),
+            OutputEncoding::Strict => match std::str::from_utf8(&buf) {
+                Ok(frame) => frame.to_string(),
+                Err(e) => {
+                    let payload = DecodeErrorEventPayload {
+                        name: source.name.clone(),
+                        instance_id: source.instance_id,
+                        stream: source.stream,
+                        error: e.to_string(),
+                        data: String::from_utf8_lossy(&buf).into_owned(),
+                    };
+                    publish(&app, source.broadcast, ProcessEvent::DecodeError(payload));
+                    continue;
+                }
+            },
+        };
+        // Numbered before throttling and batching, so held back lines leave a gap and
+        // batches sort among the lines of other streams
+        let seq = app.js().subscriptions.next_seq(&source.name);
+        if let Some(limiter) = &source.limiter {
+            let forward = limiter.admit(source.stream, captured_at, &data).await;
+            if let Some(stats) = limiter.report_due().await {
+                emit_throttled(&app, &source, limiter.policy(), stats);
+            }
+            if !forward {
+                continue;
+            }
+        }
+        if let Some((lines_tx, _)) = &batcher {
+            // The batcher only stops once this sender is dropped
+            let _ = lines_tx.send((captured_at, seq, data)).await;
+            continue;
+        }
+        let event = match source.stream {
+            StdioStream::Stdout | StdioStream::Stderr => {
+                let payload = StdioEventPayload {
+                    name: source.name.clone(),
+                    instance_id: source.instance_id,
+                    seq,
+                    stream: source.stream,
+                    captured_at,
+                    data,
+                };
+                if source.stream == StdioStream::Stdout {
+                    ProcessEvent::Stdout(payload)
+                } else {
+                    ProcessEvent::Stderr(payload)
+                }
+            }
+            StdioStream::Ipc => ProcessEvent::Ipc(IpcEventPayload {
+                name: source.name.clone(),
+                instance_id: source.instance_id,
+                connection_id: source.connection_id,
+                seq,
+                stream: source.stream,
+                captured_at,
+                data,
+            }),
+        };
+        publish(&app, source.broadcast, event);
+    }
+
+    // Wait for the last batch to go out
+    if let Some((lines_tx, task)) = batcher {
+        drop(lines_tx);
+        let _ = task.await;
+    }
+    if let Some(limiter) = &source.limiter {
+        if let Some(stats) = limiter.finish().await {
+            emit_throttled(&app, &source, limiter.policy(), stats);
+        }
+    }
+}
+
+/// Publishes the lines read by [`forward_output`] as batch events, once `interval_ms` has
+/// passed since the first line of a batch or `max_bytes` have accumulated, and flushes what
+/// is left when the stream ends.
+async fn batch_output<R: Runtime>(
+    app: AppHandle<R>,
+    source: OutputSource,
+    batch: BatchConfig,
+    mut lines_rx: mpsc::Receiver<(u64, u64, String)>,
+) {
+    let interval = Duration::from_millis(batch.interval_ms.unwrap_or(DEFAULT_BATCH_INTERVAL_MS));
+    let max_bytes = batch.max_bytes.unwrap_or(DEFAULT_BATCH_BYTES);
+    let mut lines = Vec::new();
+    let mut seqs = Vec::new();
+    let mut bytes = 0;
+    let mut captured_at = 0;
+    let mut deadline = tokio::time::Instant::now();
+    loop {
+        let received = if lines.is_empty() {
+            lines_rx.recv().await
+        } else {
+            match tokio::time::timeout_at(deadline, lines_rx.recv()).await {
+                Ok(received) => received,
+                Err(_) => {
+                    publish_batch(&app, &source, captured_at, &mut lines, &mut seqs);
+                    bytes = 0;
+                    continue;
+                }
+            }
+        };
+        let Some((at, seq, data)) = received else {
+            break;
+        };
+        if lines.is_empty() {
+            captured_at = at;
+            deadline = tokio::time::Instant::now() + interval;
+        }
+        bytes += data.len();
+        lines.push(data);
+        seqs.push(seq);
+        if bytes >= max_bytes {
+            publish_batch(&app, &source, captured_at, &mut lines, &mut seqs);
+            bytes = 0;
+        }
+    }
+    if !lines.is_empty() {
+        publish_batch(&app, &source, captured_at, &mut lines, &mut seqs);
+    }
+}
+
+fn publish_batch<R: Runtime>(
+    app: &AppHandle<R>,
+    source: &OutputSource,
+    captured_at: u64,
+    lines: &mut Vec<String>,
+    seqs: &mut Vec<u64>,
+) {
+    let payload = OutputBatchEventPayload {
+        name: source.name.clone(),
+        instance_id: source.instance_id,
+        seq: seqs[0],
+        stream: source.stream,
+        captured_at,
+        lines: std::mem::take(lines),
+        seqs: std::mem::take(seqs),
+    };
+    publish(app, source.broadcast, ProcessEvent::Batch(payload));
+}
+
+fn emit_throttled<R: Runtime>(
+    app: &AppHandle<R>,
+    source: &OutputSource,
+    policy: OverflowPolicy,
+    stats: OutputStats,
+) {
+    let payload = ThrottleEventPayload {
+        name: source.name.clone(),
+        instance_id: source.instance_id,
+        policy,
+        stats,
+    };
+    publish(app, source.broadcast, ProcessEvent::Throttled(payload));
+}
+
+/// Buffers output events, sends the event to the process's subscribers and, if `broadcast`
+/// is set, emits it to every webview as well.
+fn publish<R: Runtime>(app: &AppHandle<R>, broadcast: bool, event: ProcessEvent) {
+    app.js().subscriptions.publish(&event);
+    if broadcast {
+        let _ = match &event {
+            ProcessEvent::Stdout(payload) => app.emit("js-process-stdout", payload),
+            ProcessEvent::Stderr(payload) => app.emit("js-process-stderr", payload),
+            ProcessEvent::Ipc(payload) => app.emit("js-process-ipc", payload),
+            ProcessEvent::Batch(payload) => app.emit("js-process-output-batch", payload),
+            ProcessEvent::Ready(payload) => app.emit("js-process-ready", payload),
+            ProcessEvent::ReadinessFailed(payload) => {
+                app.emit("js-process-readiness-failed", payload)
+            }
+            ProcessEvent::Exit(payload) => app.emit("js-process-exit", payload),
+            ProcessEvent::DecodeError(payload) => app.emit("js-process-decode-error", payload),
+            ProcessEvent::Throttled(payload) => app.emit("js-process-throttled", payload),
+            ProcessEvent::Connection(payload) => app.emit("js-process-connection", payload),
+            ProcessEvent::Restart(payload) => app.emit("js-process-restart", payload),
+            ProcessEvent::CrashLoop(payload) => app.emit("js-process-crash-loop", payload),
+        };
+    }
+}
+
+/// Applies the restart policy after a process exited on its own: waits out the backoff and
+/// starts a new instance, or gives up and emits `js-process-crash-loop` when the process has
+/// been restarted too often within the window.
+///
+/// Returns a boxed future because it re-enters [`Js::start`], which spawns the exit watcher
+/// that calls back into this function.
+fn restart_after_exit<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    instance_id: u64,
+    config: SpawnConfig,
+    code: Option<i32>,
+    mut history: RestartHistory,
+) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(async move {
+        let js = app.js();
+        let broadcast = config.broadcast.unwrap_or(true);
+        let policy = config.restart_policy.clone().unwrap_or_default();
+        let window_ms = policy.window_ms.unwrap_or(DEFAULT_RESTART_WINDOW_MS);
+        let max_retries = policy.max_retries.unwrap_or(DEFAULT_MAX_RETRIES);
+
+        let now = Instant::now();
+        let window = Duration::from_millis(window_ms);
+        history.recent.retain(|at| now.duration_since(*at) < window);
+
+        let restarts = history.recent.len() as u32;
+        if restarts >= max_retries {
+            log::warn!(
+                "process '{}' restarted {} times within {}ms, giving up",
+                name,
+                restarts,
+                window_ms
+            );
+            js.mark_failed(&name, instance_id).await;
+            let payload = CrashLoopEventPayload {
+                name,
+                instance_id,
+                code,
+                restarts,
+                window_ms,
+            };
+            publish(&app, broadcast, ProcessEvent::CrashLoop(payload));
+            return;
+        }
+
+        let initial = policy
+            .initial_backoff_ms
+            .unwrap_or(DEFAULT_INITIAL_BACKOFF_MS);
+        let max = policy.max_backoff_ms.unwrap_or(DEFAULT_MAX_BACKOFF_MS);
+        let delay_ms = initial.saturating_mul(1u64 << restarts.min(16)).min(max);
+
+        history.recent.push_back(now);
+        history.total += 1;
+
+        let payload = RestartEventPayload {
+            name: name.clone(),
+            instance_id,
+            code,
+            attempt: history.total,
+            delay_ms,
+        };
+        publish(&app, broadcast, ProcessEvent::Restart(payload));
+
+        tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+
+        // kill, kill_all or an explicit spawn in the meantime cancels the restart
+        match js
+            .start(name.clone(), config, history, Some(instance_id))
+            .await
+        {
+            Ok(_) | Err(crate::Error::ProcessAlreadyExists(_)) => {}
+            Err(e) => {
+                log::error!("failed to restart process '{}': {}", name, e);
+                js.mark_failed(&name, instance_id).await;
+            }
+        }
+    })
+}
+
+impl<R: Runtime> Js<R> {
+    /// Deals with the processes previous runs left running, according to `policy`. An orphan
+    /// whose name is already taken by another one is terminated instead of reattached.
+    fn adopt_orphans(&self, found: Vec<RegistryRecord>, policy: OrphanPolicy) -> Vec<OrphanInfo> {
+        // Nothing else can hold the lock before the plugin is set up
+        let Ok(mut procs) = self.processes.try_lock() else {
+            return Vec::new();
+        };
+        let mut orphans = Vec::with_capacity(found.len());
+        for record in found {
+            let mut info = OrphanInfo {
+                name: record.name.clone(),
+                pid: record.pid,
+                started_at: record.started_at,
+                command: record.command.clone(),
+                cwd: record.cwd.clone(),
+                policy,
+                instance_id: None,
+            };
+            if policy == OrphanPolicy::Reattach && procs.contains_key(&record.name) {
+                info.policy = OrphanPolicy::Terminate;
+            }
+            match info.policy {
+                OrphanPolicy::Terminate => {
+                    // Stays registered until it is gone, in case this run does not get that far
+                    self.registry.add(record.clone());
+                    tauri::async_runtime::spawn(terminate_orphan(self.registry.clone(), record));
+                }
+                OrphanPolicy::Reattach => {
+                    info.instance_id = Some(self.reattach(&mut procs, record));
+                }
+                OrphanPolicy::Ignore => {}
+            }
+            log::warn!(
+                "process '{}' (pid {}) was left running by a previous run of the app, policy: {:?}",
+                info.name,
+                info.pid,
+                info.policy
+            );
+            orphans.push(info);
+        }
+        orphans
+    }
+
+    /// Tracks an orphan as a new `Orphaned` instance, returning its instance id. The config
+    /// only records its command line, which is all the registry keeps; its pipes were lost
+    /// with the previous run.
+    fn reattach(&self, procs: &mut HashMap<String, ProcessEntry>, record: RegistryRecord) -> u64 {
+        let instance_id = self.next_instance_id.fetch_add(1, Ordering::Relaxed);
+        let (kill_tx, kill_rx) = oneshot::channel();
+        let (exit_senders, reaped_rx, exit_rx) = ExitSenders::channel();
+        let stop_reason = Arc::new(OnceLock::new());
+        let mut command = record.command.iter().cloned();
+        let config = SpawnConfig {
+            command: command.next(),
+            args: Some(command.collect()),
+            cwd: record.cwd.clone(),
+            process_group: Some(record.process_group),
+            ..SpawnConfig::default()
+        };
+        let entry = ProcessEntry {
+            info: ProcessInfo {
+                name: record.name.clone(),
+                instance_id,
+                pid: Some(record.pid),
+                state: ProcessState::Orphaned,
+                running: true,
+                started_at: record.started_at,
+                exited_at: None,
+                exit_code: None,
+                exit_signal: None,
+                exit_reason: None,
+                restart_count: 0,
+                config,
+                output_stats: OutputStats::default(),
+            },
+            stdin: None,
+            ipc: None,
+            connections: HashMap::new(),
+            socket_guard: None,
+            kill_tx: Some(kill_tx),
+            stop_reason: stop_reason.clone(),
+            reaped_rx,
+            exit_rx,
+            ready_rx: watch::channel(None).1,
+            limiter: None,
+            resume_state: None,
+            pty: None,
+            orphan_start_time: Some(record.start_time.clone()),
+            replaced: procs
+                .get_mut(&record.name)
+                .map(ProcessEntry::take_replaced)
+                .unwrap_or_default(),
+        };
+        procs.insert(record.name.clone(), entry);
+
+        let record = RegistryRecord {
+            instance_id,
+            ..record
+        };
+        self.registry.add(record.clone());
+        tauri::async_runtime::spawn(watch_orphan(
+            self.app.clone(),
+            self.processes.clone(),
+            self.registry.clone(),
+            record,
+            stop_reason,
+            kill_rx,
+            exit_senders,
+        ));
+        instance_id
+    }
+
+    /// Gives up on restarting `instance_id`, if it is still waiting to be restarted.
+    async fn mark_failed(&self, name: &str, instance_id: u64) {
+        let mut procs = self.processes.lock().await;
+        if let Some(entry) = procs.get_mut(name).filter(|entry| {
+            entry.info.instance_id == instance_id && entry.info.state == ProcessState::Restarting
+        }) {
+            entry.set_state(ProcessState::Failed);
+        }
+    }
+}
+
+/// Exit watcher of a reattached orphan. It polls the pid until the process is gone, then
+/// records the exit like the exit watcher of a spawned process; the exit code is unknown.
+async fn watch_orphan<R: Runtime>(
+    app: AppHandle<R>,
+    processes: Arc<Mutex<HashMap<String, ProcessEntry>>>,
+    registry: Arc<Registry>,
+    record: RegistryRecord,
+    stop_reason: Arc<OnceLock<ExitReason>>,
+    mut kill_rx: oneshot::Receiver<()>,
+    exit_senders: ExitSenders,
+) {
+    let mut kill_requested = false;
+    loop {
+        tokio::select! {
+            // A dropped sender means the entry was discarded, not a kill request
+            result = &mut kill_rx, if !kill_requested => {
+                kill_requested = true;
+                if result.is_ok() {
+                    signal_orphan(&record, true);
+                }
+            }
+            _ = tokio::time::sleep(ORPHAN_POLL_INTERVAL) => {}
+        }
+        if !record.is_running() {
+            break;
+        }
+    }
+    let _ = exit_senders.reaped.send(true);
+    registry.remove(record.pid, &record.start_time);
+
+    let reason = stop_reason.get().copied().unwrap_or(ExitReason::Exited);
+    {
+        let mut procs = processes.lock().await;
+        if let Some(entry) = procs
+            .get_mut(&record.name)
+            .filter(|entry| entry.info.instance_id == record.instance_id)
+        {
+            entry.set_state(settled_state(Some(reason)));
+            entry.info.exited_at = Some(now_ms());
+            entry.info.exit_reason = Some(reason);
+            entry.kill_tx = None;
+            entry.resume_state = None;
+        }
+        for name in prune_exited(&mut procs) {
+            app.js().subscriptions.remove_replay(&name);
+        }
+    }
+
+    let payload = ExitEventPayload {
+        name: record.name.clone(),
+        instance_id: record.instance_id,
+        code: None,
+        signal: None,
+        reason,
+        uptime_ms: now_ms().saturating_sub(record.started_at),
+    };
+    publish(&app, true, ProcessEvent::Exit(payload.clone()));
+    let _ = exit_senders.exited.send(Some(Exited {
+        status: None,
+        event: payload,
+    }));
+}
+
+/// Stops an orphan with SIGTERM, then SIGKILL if it is still running after
+/// [`ORPHAN_TERMINATE_TIMEOUT`].
+async fn terminate_orphan(registry: Arc<Registry>, record: RegistryRecord) {
+    if signal_orphan(&record, false) {
+        let deadline = Instant::now() + ORPHAN_TERMINATE_TIMEOUT;
+        while record.is_running() && Instant::now() < deadline {
+            tokio::time::sleep(ORPHAN_POLL_INTERVAL).await;
+        }
+        if signal_orphan(&record, true) {
+            log::warn!(
+                "process '{}' (pid {}) did not exit on SIGTERM and was killed",
+                record.name,
+                record.pid
+            );
+        }
+    }
+    registry.remove(record.pid, &record.start_time);
+}
+
+/// Sends SIGTERM, or SIGKILL if `force` is set, to an orphan and its group if it leads one.
+/// Returns whether it was signalled, which it is not once its pid belongs to another process.
+#[cfg(unix)]
+fn signal_orphan(record: &RegistryRecord, force: bool) -> bool {
+    if !record.is_running() {
+        return false;
+    }
+    let signal = if force { libc::SIGKILL } else { libc::SIGTERM };
+    let kill = if record.process_group {
+        libc::killpg
+    } else {
+        libc::kill
+    };
+    // SAFETY: the pid was just checked to still belong to the orphan
+    unsafe { kill(record.pid as libc::pid_t, signal) == 0 }
+}
+
+#[cfg(not(unix))]
+fn signal_orphan(_record: &RegistryRecord, _force: bool) -> bool {
+    false
+}
+
+/// Whether `pid` still refers to the process, for orphans whose start time is known; other
+/// processes are our children, whose pid cannot be reused before they are reaped.
+fn is_same_process(pid: u32, orphan_start_time: Option<&str>) -> bool {
+    orphan_start_time.map_or(true, |t| registry::start_time(pid).is_some_and(|s| s == t))
+}
+
+/// Stops a process taken out of service by [`ProcessEntry::begin_stop`]: optionally writes
+/// the shutdown message, closes stdin, sends the shutdown signal, then waits until the
+/// configured timeout is up before force-killing it. Writing the message counts against the
+/// same timeout.
+async fn shutdown(mut handle: StopHandle) -> ShutdownResult {
+    let started = Instant::now();
+    let shutdown = handle.shutdown.clone();
+    let timeout = Duration::from_millis(shutdown.timeout_ms.unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT_MS));
+    let deadline = tokio::time::Instant::from_std(started + timeout);
+
+    if handle.has_exited() {
+        let status = handle.exited().await;
+        return shutdown_result(handle, ShutdownOutcome::AlreadyExited, status, started);
+    }
+
+    // Deliver the shutdown message after any queued writes, then drop stdin to signal EOF
+    let stdin = handle.stdin.take();
+    let message_sent = match (&stdin, &shutdown.message) {
+        (Some(stdin), Some(message)) => {
+            let write = async {
+                let frame = framing::encode_frame(handle.framing, message.as_bytes())?;
+                stdin.write(frame).await
+            };
+            matches!(tokio::time::timeout_at(deadline, write).await, Ok(Ok(())))
+        }
+        _ => false,
+    };
+    drop(stdin);
+
+    // The message is the request to stop, so the default signal only goes out without one
+    #[cfg(unix)]
+    if let Some(pid) = handle.live_pid() {
+        let signal = match shutdown.signal.as_deref() {
+            Some(signal) => signal_number(signal),
+            None if message_sent => None,
+            None => Some(libc::SIGTERM),
+        };
+        let kill = if handle.group {
+            libc::killpg
+        } else {
+            libc::kill
+        };
+        // SAFETY: `pid` still refers to the process and its group: a child is not reaped until
+        // the exit watcher sees it exit, and an orphan's start time was just checked
+        unsafe {
+            if let Some(signal) = signal {
+                kill(pid as libc::pid_t, signal);
+            }
+            // A stopped process only acts on the signal or message once it is continued
+            if handle.suspended {
+                kill(pid as libc::pid_t, libc::SIGCONT);
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = message_sent;
+
+    // Only the child counts toward the timeout, not draining output that the rest of its
+    // group may be keeping open
+    match tokio::time::timeout_at(deadline, handle.reaped()).await {
+        Ok(()) => {
+            #[cfg(unix)]
+            if let Some(pid) = handle.pid.filter(|_| handle.group) {
+                reap_group(pid, timeout.saturating_sub(started.elapsed())).await;
+            }
+            let status = handle.exited().await;
+            shutdown_result(handle, ShutdownOutcome::Graceful, status, started)
+        }
+        Err(_) => {
+            handle.force_kill();
+            let status = handle.exited().await;
+            shutdown_result(handle, ShutdownOutcome::Killed, status, started)
+        }
+    }
+}
+
+/// Gives the rest of a process group until `timeout` to exit after its leader did, then
+/// force-kills whatever is left, so nothing keeps the process's pipes open.
+#[cfg(unix)]
+async fn reap_group(pgid: u32, timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    // Signal 0 only checks for members; it fails with ESRCH once the group is empty
+    // SAFETY: a group id cannot be reused as long as the group has members
+    while unsafe { libc::killpg(pgid as libc::pid_t, 0) } == 0 {
+        if Instant::now() >= deadline {
+            // SAFETY: as above, the group still has members
+            unsafe {
+                libc::killpg(pgid as libc::pid_t, libc::SIGKILL);
+            }
+            return;
+        }
+        tokio::time::sleep(GROUP_POLL_INTERVAL).await;
+    }
+}
+
+fn shutdown_result(
+    handle: StopHandle,
+    outcome: ShutdownOutcome,
+    status: Option<ExitStatus>,
+    started: Instant,
+) -> ShutdownResult {
+    ShutdownResult {
+        name: handle.name,
+        instance_id: handle.instance_id,
+        outcome,
+        code: status.and_then(|s| s.code()),
+        signal: status.and_then(|s| exit_signal(&s)),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+/// Framing of stdout and stdin; with a dedicated IPC pipe or socket, `framing` applies to that instead
+/// and stdio is plain lines. A pty passes bytes through as they come, like a terminal.
+fn stdio_framing(config: &SpawnConfig) -> Framing {
+    if config.pty.unwrap_or(false) {
+        Framing::Raw
+    } else if config.ipc.unwrap_or(false) || config.socket.unwrap_or(false) {
+        Framing::Newline
+    } else {
+        config.framing.unwrap_or_default()
+    }
+}
+
+/// Maps a failed queued write to [`crate::Error::WriteQueueFull`] if the queue was full,
+/// otherwise to the error built by `error`.
+fn write_error(
+    name: String,
+    e: std::io::Error,
+    error: fn(String, String) -> crate::Error,
+) -> crate::Error {
+    if e.kind() == std::io::ErrorKind::WouldBlock {
+        crate::Error::WriteQueueFull(name)
+    } else {
+        error(name, e.to_string())
+    }
+}
+
+/// Sets a parent-death signal on the child, so it is SIGKILLed as soon as the app dies.
+/// Linux ties the signal to the thread that spawned the child rather than the whole process;
+/// spawns run on the async runtime's worker threads, which live as long as the app.
+#[cfg(target_os = "linux")]
+fn kill_on_parent_death(cmd: &mut Command) {
+    let parent = std::process::id() as libc::pid_t;
+    // SAFETY: the closure only calls async-signal-safe functions
+    unsafe {
+        cmd.pre_exec(move || {
+            if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            // The app may have died before the signal was set, which would go unnoticed
+            if libc::getppid() != parent {
+                return Err(std::io::Error::from_raw_os_error(libc::ESRCH));
+            }
+            Ok(())
+        });
+    }
+}
+
+/// State an exited process settles in once it is not going to be restarted.
+fn settled_state(reason: Option<ExitReason>) -> ProcessState {
+    match reason {
+        Some(ExitReason::Crashed) => ProcessState::Failed,
+        _ => ProcessState::Exited,
+    }
+}
+
+/// Drops the oldest exited processes beyond [`MAX_RETAINED_EXITED`], returning their names.
+fn prune_exited(procs: &mut HashMap<String, ProcessEntry>) -> Vec<String> {
+    let mut exited: Vec<(u64, String)> = procs
+        .iter()
+        .filter(|(_, e)| matches!(e.info.state, ProcessState::Exited | ProcessState::Failed))
+        .map(|(name, e)| (e.info.exited_at.unwrap_or_default(), name.clone()))
+        .collect();
+    if exited.len() <= MAX_RETAINED_EXITED {
+        return Vec::new();
+    }
+    exited.sort();
+    exited
+        .drain(..exited.len() - MAX_RETAINED_EXITED)
+        .map(|(_, name)| {
+            procs.remove(&name);
+            name
+        })
+        .collect()
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(unix)]
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+#[cfg(unix)]
+fn signal_number(name: &str) -> Option<libc::c_int> {
+    match name {
+        "SIGTERM" => Some(libc::SIGTERM),
+        "SIGINT" => Some(libc::SIGINT),
+        "SIGHUP" => Some(libc::SIGHUP),
+        "SIGQUIT" => Some(libc::SIGQUIT),
+        "SIGUSR1" => Some(libc::SIGUSR1),
+        "SIGUSR2" => Some(libc::SIGUSR2),
+        "SIGSTOP" => Some(libc::SIGSTOP),
+        "SIGCONT" => Some(libc::SIGCONT),
+        "SIGWINCH" => Some(libc::SIGWINCH),
+        _ => None,
+    }
+}
+
+#[cfg(unix)]
+fn send_signal(pid: u32, name: &str) -> std::io::Result<()> {
+    let signal = signal_number(name).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("unknown signal: {name}"),
+        )
+    })?;
+    // SAFETY: callers check that the child has not been reaped, so `pid` still refers to it
+    if unsafe { libc::kill(pid as libc::pid_t, signal) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send_signal(_pid: u32, _name: &str) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "signals are only supported on Unix",
+    ))
+}
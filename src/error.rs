@@ -12,10 +12,24 @@ pub enum Error {
     ProcessAlreadyExists(String),
     #[error("process not running: {0}")]
     ProcessNotRunning(String),
+    #[error("process instance not running: {0} (instance {1})")]
+    InstanceNotRunning(String, u64),
     #[error("invalid config: {0}")]
     InvalidConfig(String),
     #[error("stdin write error for '{0}': {1}")]
     StdinWriteError(String, String),
+    #[error("stdin closed: {0}")]
+    StdinClosed(String),
+    #[error("ipc write error for '{0}': {1}")]
+    IpcWriteError(String, String),
+    #[error("write queue full for '{0}'")]
+    WriteQueueFull(String),
+    #[error("timed out waiting for '{0}'")]
+    WaitTimeout(String),
+    #[error("'{0}' exited before the expected output appeared")]
+    ExitedBeforeMatch(String),
+    #[error("'{0}' did not become ready: {1}")]
+    NotReady(String, String),
     #[cfg(mobile)]
     #[error(transparent)]
     PluginInvoke(#[from] tauri::plugin::mobile::PluginInvokeError),
@@ -0,0 +1,209 @@
+//! How a child's stdout byte stream is split into messages, and how stdin writes are framed
+//! to match.
+
+use std::io;
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+use crate::models::Framing;
+
+/// Largest frame accepted in either direction in length-prefixed mode.
+const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// Maximum number of bytes emitted per chunk in raw mode.
+const RAW_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Reads the next frame into `buf`, without its delimiter or length header.
+/// Returns `Ok(false)` once the stream has ended. A length-prefixed frame over the size limit
+/// is skipped and reported as [`io::ErrorKind::InvalidData`], leaving the reader at the start
+/// of the next frame.
+pub(crate) async fn read_frame<Rd: AsyncBufRead + Unpin>(
+    reader: &mut Rd,
+    framing: Framing,
+    buf: &mut Vec<u8>,
+) -> io::Result<bool> {
+    buf.clear();
+    match framing {
+        Framing::Newline => {
+            if reader.read_until(b'\n', buf).await? == 0 {
+                return Ok(false);
+            }
+            // Strip the line ending like `BufRead::lines` does
+            if buf.last() == Some(&b'\n') {
+                buf.pop();
+                if buf.last() == Some(&b'\r') {
+                    buf.pop();
+                }
+            }
+        }
+        Framing::Nul => {
+            if reader.read_until(0, buf).await? == 0 {
+                return Ok(false);
+            }
+            if buf.last() == Some(&0) {
+                buf.pop();
+            }
+        }
+        Framing::LengthPrefixed => {
+            let mut header = [0u8; 4];
+            match reader.read_exact(&mut header).await {
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+                Err(e) => return Err(e),
+            }
+            let len = u32::from_be_bytes(header) as usize;
+            if len > MAX_FRAME_LEN {
+                tokio::io::copy(&mut reader.take(len as u64), &mut tokio::io::sink()).await?;
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("skipped a frame of {len} bytes, over the {MAX_FRAME_LEN} byte limit"),
+                ));
+            }
+            buf.resize(len, 0);
+            reader.read_exact(buf).await?;
+        }
+        Framing::Raw => {
+            buf.resize(RAW_CHUNK_SIZE, 0);
+            let n = reader.read(buf).await?;
+            buf.truncate(n);
+            if n == 0 {
+                return Ok(false);
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// Frames `data` for writing to a child's stdin. Newline and raw data is written as is, since
+/// callers already terminate their lines; NUL framing appends the delimiter and
+/// length-prefixed framing prepends a 4-byte big-endian length.
+pub(crate) fn encode_frame(framing: Framing, data: &[u8]) -> io::Result<Vec<u8>> {
+    match framing {
+        Framing::Newline | Framing::Raw => Ok(data.to_vec()),
+        Framing::Nul => {
+            let mut frame = Vec::with_capacity(data.len() + 1);
+            frame.extend_from_slice(data);
+            frame.push(0);
+            Ok(frame)
+        }
+        Framing::LengthPrefixed => {
+            if data.len() > MAX_FRAME_LEN {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "frame of {} bytes exceeds the {MAX_FRAME_LEN} byte limit",
+                        data.len()
+                    ),
+                ));
+            }
+            let mut frame = Vec::with_capacity(data.len() + 4);
+            frame.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            frame.extend_from_slice(data);
+            Ok(frame)
+        }
+    }
+}
+
+/// Splits off a UTF-8 sequence cut short at the end of `buf`, so raw chunks can be decoded
+/// without mangling characters that straddle two reads.
+pub(crate) fn split_incomplete_utf8(buf: &mut Vec<u8>) -> Vec<u8> {
+    match std::str::from_utf8(buf) {
+        Err(e) if e.error_len().is_none() => buf.split_off(e.valid_up_to()),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn read_all(mut input: &[u8], framing: Framing) -> io::Result<Vec<Vec<u8>>> {
+        let mut frames = Vec::new();
+        let mut buf = Vec::new();
+        while read_frame(&mut input, framing, &mut buf).await? {
+            frames.push(buf.clone());
+        }
+        Ok(frames)
+    }
+
+    #[tokio::test]
+    async fn newline_strips_line_endings() {
+        let frames = read_all(b"a\r\nb\n\r\nc", Framing::Newline).await.unwrap();
+        assert_eq!(frames, [&b"a"[..], b"b", b"", b"c"]);
+    }
+
+    #[tokio::test]
+    async fn nul_splits_on_nul_bytes() {
+        let frames = read_all(b"a\nb\0c\0", Framing::Nul).await.unwrap();
+        assert_eq!(frames, [&b"a\nb"[..], b"c"]);
+    }
+
+    #[tokio::test]
+    async fn length_prefixed_ends_on_partial_header() {
+        let frames = read_all(b"\0\0\0\x02hi\0\0", Framing::LengthPrefixed)
+            .await
+            .unwrap();
+        assert_eq!(frames, [b"hi"]);
+    }
+
+    #[tokio::test]
+    async fn length_prefixed_fails_on_partial_payload() {
+        let err = read_all(b"\0\0\0\x05hi", Framing::LengthPrefixed)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn length_prefixed_skips_oversized_frames() {
+        let len = MAX_FRAME_LEN + 1;
+        let mut input = (len as u32).to_be_bytes().to_vec();
+        input.resize(4 + len, b'x');
+        input.extend_from_slice(b"\0\0\0\x02ok");
+        let mut reader = input.as_slice();
+        let mut buf = Vec::new();
+
+        let err = read_frame(&mut reader, Framing::LengthPrefixed, &mut buf)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(read_frame(&mut reader, Framing::LengthPrefixed, &mut buf)
+            .await
+            .unwrap());
+        assert_eq!(buf, b"ok");
+    }
+
+    #[test]
+    fn encode_frame_matches_read_frame() {
+        assert_eq!(encode_frame(Framing::Nul, b"a").unwrap(), b"a\0");
+        assert_eq!(
+            encode_frame(Framing::LengthPrefixed, b"ab").unwrap(),
+            b"\0\0\0\x02ab"
+        );
+        assert_eq!(encode_frame(Framing::Newline, b"a\n").unwrap(), b"a\n");
+    }
+
+    #[test]
+    fn split_incomplete_utf8_keeps_cut_off_sequences() {
+        // "é" is C3 A9, "€" is E2 82 AC
+        let mut buf = b"ab\xc3".to_vec();
+        assert_eq!(split_incomplete_utf8(&mut buf), b"\xc3");
+        assert_eq!(buf, b"ab");
+
+        let mut buf = b"a\xe2\x82".to_vec();
+        assert_eq!(split_incomplete_utf8(&mut buf), b"\xe2\x82");
+        assert_eq!(buf, b"a");
+
+        let mut buf = "aé€".as_bytes().to_vec();
+        assert!(split_incomplete_utf8(&mut buf).is_empty());
+        assert_eq!(buf, "aé€".as_bytes());
+    }
+
+    #[test]
+    fn split_incomplete_utf8_leaves_invalid_bytes() {
+        // Invalid rather than incomplete, so lossy decoding replaces it
+        let mut buf = b"a\xff".to_vec();
+        assert!(split_incomplete_utf8(&mut buf).is_empty());
+        assert_eq!(buf, b"a\xff");
+    }
+}
@@ -0,0 +1,213 @@
+//! Dedicated RPC channels to a child, so messages don't share stdout with its logs: a pipe
+//! pair handed over next to its stdio, or a private Unix socket it connects to.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::process::Command;
+
+/// Environment variable telling the child which fds to use, as `"<read>,<write>"`.
+#[cfg(unix)]
+const IPC_FDS_ENV: &str = "TAURI_JS_IPC_FDS";
+
+/// fd the child reads messages from.
+#[cfg(unix)]
+const CHILD_READ_FD: libc::c_int = 3;
+/// fd the child writes messages to.
+#[cfg(unix)]
+const CHILD_WRITE_FD: libc::c_int = 4;
+
+/// Environment variable holding the path of the process's socket.
+pub(crate) const SOCKET_ENV: &str = "TAURI_JS_SOCKET";
+
+pub(crate) type IpcWriter = Box<dyn AsyncWrite + Send + Unpin>;
+pub(crate) type IpcReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// Pipes set up for a command that has not been spawned yet.
+#[cfg(unix)]
+pub(crate) struct PendingIpc {
+    /// The child's ends, closed in the parent once the child holds its copies
+    child_ends: [std::os::fd::OwnedFd; 2],
+    writer: std::os::fd::OwnedFd,
+    reader: std::os::fd::OwnedFd,
+}
+
+#[cfg(not(unix))]
+pub(crate) struct PendingIpc;
+
+/// Creates the pipe pair and arranges for the child to receive it on fds 3 and 4.
+#[cfg(unix)]
+pub(crate) fn attach(cmd: &mut Command) -> io::Result<PendingIpc> {
+    use std::os::fd::AsRawFd;
+
+    let (child_read, writer) = pipe()?;
+    let (reader, child_write) = pipe()?;
+    let (read_fd, write_fd) = (child_read.as_raw_fd(), child_write.as_raw_fd());
+
+    cmd.env(IPC_FDS_ENV, format!("{CHILD_READ_FD},{CHILD_WRITE_FD}"));
+    // SAFETY: the closure only calls async-signal-safe functions
+    unsafe {
+        cmd.pre_exec(move || {
+            // Move both ends above the target fds first, so neither dup2 clobbers the other.
+            // The copies are close-on-exec; dup2 clears the flag on the targets.
+            let read_fd = cvt(libc::fcntl(
+                read_fd,
+                libc::F_DUPFD_CLOEXEC,
+                CHILD_WRITE_FD + 1,
+            ))?;
+            let write_fd = cvt(libc::fcntl(
+                write_fd,
+                libc::F_DUPFD_CLOEXEC,
+                CHILD_WRITE_FD + 1,
+            ))?;
+            cvt(libc::dup2(read_fd, CHILD_READ_FD))?;
+            cvt(libc::dup2(write_fd, CHILD_WRITE_FD))?;
+            Ok(())
+        });
+    }
+
+    Ok(PendingIpc {
+        child_ends: [child_read, child_write],
+        writer,
+        reader,
+    })
+}
+
+#[cfg(not(unix))]
+pub(crate) fn attach(_cmd: &mut Command) -> io::Result<PendingIpc> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "ipc pipes are only supported on Unix",
+    ))
+}
+
+impl PendingIpc {
+    /// Closes the child's ends once it has been spawned and returns the parent's.
+    #[cfg(unix)]
+    pub(crate) fn finish(self) -> io::Result<(IpcWriter, IpcReader)> {
+        use tokio::net::unix::pipe;
+
+        drop(self.child_ends);
+        let writer = pipe::Sender::from_owned_fd(self.writer)?;
+        let reader = pipe::Receiver::from_owned_fd(self.reader)?;
+        Ok((Box::new(writer), Box::new(reader)))
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn finish(self) -> io::Result<(IpcWriter, IpcReader)> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "ipc pipes are only supported on Unix",
+        ))
+    }
+}
+
+/// A listening socket, removed from disk when dropped.
+#[cfg(unix)]
+pub(crate) struct IpcSocket {
+    listener: tokio::net::UnixListener,
+    path: PathBuf,
+}
+
+#[cfg(not(unix))]
+pub(crate) enum IpcSocket {}
+
+#[cfg(unix)]
+impl IpcSocket {
+    /// Binds a socket at `path`, creating its directory private to the current user.
+    pub(crate) fn bind(path: PathBuf) -> io::Result<Self> {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+        if let Some(dir) = path.parent() {
+            std::fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(dir)?;
+            std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+        }
+        // A leftover from a previous run of the app would make bind fail
+        match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        let listener = tokio::net::UnixListener::bind(&path)?;
+        Ok(Self { listener, path })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Waits for the next connection and splits it into its two directions.
+    pub(crate) async fn accept(&self) -> io::Result<(IpcReader, IpcWriter)> {
+        let (stream, _) = self.listener.accept().await?;
+        let (reader, writer) = stream.into_split();
+        Ok((Box::new(reader), Box::new(writer)))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for IpcSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(not(unix))]
+impl IpcSocket {
+    pub(crate) fn bind(_path: PathBuf) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "ipc sockets are only supported on Unix",
+        ))
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        match *self {}
+    }
+
+    pub(crate) async fn accept(&self) -> io::Result<(IpcReader, IpcWriter)> {
+        match *self {}
+    }
+}
+
+/// Creates a pipe whose ends are both close-on-exec, returning `(read, write)`.
+#[cfg(unix)]
+fn pipe() -> io::Result<(std::os::fd::OwnedFd, std::os::fd::OwnedFd)> {
+    use std::os::fd::FromRawFd;
+
+    let mut fds = [0 as libc::c_int; 2];
+    // Close-on-exec from the start, so a process spawned concurrently never inherits them
+    // SAFETY: `fds` has room for the two descriptors pipe2() writes
+    #[cfg(not(target_os = "macos"))]
+    cvt(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) })?;
+    // SAFETY: `fds` has room for the two descriptors pipe() writes
+    #[cfg(target_os = "macos")]
+    cvt(unsafe { libc::pipe(fds.as_mut_ptr()) })?;
+    // SAFETY: the pipe was created, so both descriptors are open and owned by nobody else
+    let (read, write) = unsafe {
+        (
+            std::os::fd::OwnedFd::from_raw_fd(fds[0]),
+            std::os::fd::OwnedFd::from_raw_fd(fds[1]),
+        )
+    };
+    // macOS has no pipe2(), so a process spawned on another thread in between can still
+    // inherit the pipe
+    #[cfg(target_os = "macos")]
+    for fd in fds {
+        // SAFETY: `fd` is open for the duration of the call
+        cvt(unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) })?;
+    }
+    Ok((read, write))
+}
+
+/// Turns the `-1` a libc call returns on failure into the error in `errno`.
+#[cfg(unix)]
+pub(crate) fn cvt(ret: libc::c_int) -> io::Result<libc::c_int> {
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
@@ -1,14 +1,33 @@
 use tauri::{
     plugin::{Builder, TauriPlugin},
-    Manager, RunEvent, Runtime,
+    webview::PageLoadEvent,
+    Manager, RunEvent, Runtime, WindowEvent,
 };
 
 pub use models::*;
 
 #[cfg(desktop)]
 mod desktop;
+#[cfg(desktop)]
+mod framing;
+#[cfg(desktop)]
+mod ipc;
+#[cfg(desktop)]
+mod matcher;
 #[cfg(mobile)]
 mod mobile;
+#[cfg(desktop)]
+mod pty;
+#[cfg(desktop)]
+mod readiness;
+#[cfg(desktop)]
+mod registry;
+#[cfg(desktop)]
+mod subscriptions;
+#[cfg(desktop)]
+mod throttle;
+#[cfg(desktop)]
+mod write_queue;
 
 mod commands;
 mod error;
@@ -33,16 +52,27 @@ impl<R: Runtime, T: Manager<R>> crate::JsExt<R> for T {
 }
 
 /// Initializes the plugin.
-pub fn init<R: Runtime>() -> TauriPlugin<R> {
-    Builder::new("js")
+pub fn init<R: Runtime>() -> TauriPlugin<R, Option<PluginConfig>> {
+    Builder::<R, Option<PluginConfig>>::new("js")
         .invoke_handler(tauri::generate_handler![
             commands::spawn,
+            commands::exec,
             commands::kill,
             commands::kill_all,
             commands::restart,
             commands::list_processes,
+            commands::list_orphans,
             commands::get_status,
+            commands::wait,
+            commands::wait_for_output,
+            commands::subscribe,
+            commands::unsubscribe,
             commands::write_stdin,
+            commands::write_stdin_bytes,
+            commands::close_stdin,
+            commands::write_ipc,
+            commands::signal,
+            commands::resize,
             commands::detect_runtimes,
             commands::set_runtime_path,
             commands::get_runtime_paths,
@@ -55,13 +85,34 @@ pub fn init<R: Runtime>() -> TauriPlugin<R> {
             app.manage(js);
             Ok(())
         })
-        .on_event(|app, event| {
-            if let RunEvent::Exit = event {
+        .on_page_load(|webview, payload| {
+            // Channels die with the page that created them
+            if payload.event() == PageLoadEvent::Started {
+                webview.js().unsubscribe_webview(webview.label());
+            }
+        })
+        .on_event(|app, event| match event {
+            RunEvent::WindowEvent {
+                label,
+                event: WindowEvent::Destroyed,
+                ..
+            } => app.js().unsubscribe_window(label),
+            RunEvent::Exit => {
                 let js = app.state::<Js<R>>();
                 tauri::async_runtime::block_on(async {
-                    let _ = js.kill_all().await;
+                    if let Ok(results) = js.kill_all().await {
+                        for result in results {
+                            if result.outcome == ShutdownOutcome::Killed {
+                                log::warn!(
+                                    "process '{}' did not exit within its shutdown timeout and was killed",
+                                    result.name
+                                );
+                            }
+                        }
+                    }
                 });
             }
+            _ => {}
         })
         .build()
 }
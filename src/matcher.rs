@@ -0,0 +1,207 @@
+//! Patterns that output events are matched against, line by line, when waiting for a process
+//! to print something.
+
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use crate::models::{OutputMatch, ProcessEvent, StdioStream};
+
+pub(crate) enum OutputPattern {
+    Substring(String),
+    Regex(Regex),
+}
+
+/// Groups captured from a matching line, see [`OutputMatch`].
+struct LineMatch {
+    captures: Vec<Option<String>>,
+    named_captures: HashMap<String, String>,
+}
+
+impl OutputPattern {
+    pub(crate) fn new(pattern: &str, regex: bool) -> crate::Result<Self> {
+        if !regex {
+            return Ok(Self::Substring(pattern.to_string()));
+        }
+        Regex::new(pattern)
+            .map(Self::Regex)
+            .map_err(|e| crate::Error::InvalidConfig(format!("invalid pattern: {}", e)))
+    }
+
+    /// First line of `event` that matches, if the event is output of `instance_id` on
+    /// `stream`. Each line of a batch is tried in turn.
+    pub(crate) fn find(
+        &self,
+        event: &ProcessEvent,
+        instance_id: u64,
+        stream: StdioStream,
+    ) -> Option<OutputMatch> {
+        let (name, event_instance, event_stream, captured_at, lines, seqs) = match event {
+            ProcessEvent::Stdout(p) | ProcessEvent::Stderr(p) => (
+                &p.name,
+                p.instance_id,
+                p.stream,
+                p.captured_at,
+                std::slice::from_ref(&p.data),
+                std::slice::from_ref(&p.seq),
+            ),
+            ProcessEvent::Ipc(p) => (
+                &p.name,
+                p.instance_id,
+                p.stream,
+                p.captured_at,
+                std::slice::from_ref(&p.data),
+                std::slice::from_ref(&p.seq),
+            ),
+            ProcessEvent::Batch(p) => (
+                &p.name,
+                p.instance_id,
+                p.stream,
+                p.captured_at,
+                p.lines.as_slice(),
+                p.seqs.as_slice(),
+            ),
+            _ => return None,
+        };
+        if event_instance != instance_id || event_stream != stream {
+            return None;
+        }
+        lines.iter().zip(seqs).find_map(|(line, &seq)| {
+            let LineMatch {
+                captures,
+                named_captures,
+            } = self.match_line(line)?;
+            Some(OutputMatch {
+                name: name.clone(),
+                instance_id,
+                seq,
+                stream,
+                captured_at,
+                line: line.clone(),
+                captures,
+                named_captures,
+            })
+        })
+    }
+
+    fn match_line(&self, line: &str) -> Option<LineMatch> {
+        match self {
+            Self::Substring(pattern) => line.contains(pattern.as_str()).then(|| LineMatch {
+                captures: vec![Some(pattern.clone())],
+                named_captures: HashMap::new(),
+            }),
+            Self::Regex(regex) => {
+                let captures = regex.captures(line)?;
+                let named_captures = regex
+                    .capture_names()
+                    .flatten()
+                    .filter_map(|group| {
+                        let value = captures.name(group)?;
+                        Some((group.to_string(), value.as_str().to_string()))
+                    })
+                    .collect();
+                Some(LineMatch {
+                    captures: captures
+                        .iter()
+                        .map(|group| group.map(|m| m.as_str().to_string()))
+                        .collect(),
+                    named_captures,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{OutputBatchEventPayload, StdioEventPayload};
+
+    fn stdout(instance_id: u64, seq: u64, data: &str) -> ProcessEvent {
+        ProcessEvent::Stdout(StdioEventPayload {
+            name: "p".to_string(),
+            instance_id,
+            seq,
+            stream: StdioStream::Stdout,
+            captured_at: 1000,
+            data: data.to_string(),
+        })
+    }
+
+    fn batch(lines: &[&str], seqs: &[u64]) -> ProcessEvent {
+        ProcessEvent::Batch(OutputBatchEventPayload {
+            name: "p".to_string(),
+            instance_id: 1,
+            seq: seqs[0],
+            stream: StdioStream::Stdout,
+            captured_at: 1000,
+            lines: lines.iter().map(|line| line.to_string()).collect(),
+            seqs: seqs.to_vec(),
+        })
+    }
+
+    #[test]
+    fn substring_captures_the_pattern() {
+        let pattern = OutputPattern::new("ready", false).unwrap();
+        let found = pattern
+            .find(&stdout(1, 7, "server ready"), 1, StdioStream::Stdout)
+            .unwrap();
+        assert_eq!(found.seq, 7);
+        assert_eq!(found.line, "server ready");
+        assert_eq!(found.captures, [Some("ready".to_string())]);
+        assert!(pattern
+            .find(&stdout(1, 8, "starting"), 1, StdioStream::Stdout)
+            .is_none());
+    }
+
+    #[test]
+    fn regex_captures_groups() {
+        let pattern = OutputPattern::new(r"port (?<port>\d+)(?: on (\w+))?", true).unwrap();
+        let found = pattern
+            .find(
+                &stdout(1, 1, "listening on port 3000"),
+                1,
+                StdioStream::Stdout,
+            )
+            .unwrap();
+        assert_eq!(
+            found.captures,
+            [
+                Some("port 3000".to_string()),
+                Some("3000".to_string()),
+                None
+            ]
+        );
+        assert_eq!(found.named_captures["port"], "3000");
+        assert_eq!(found.named_captures.len(), 1);
+    }
+
+    #[test]
+    fn invalid_regex_is_rejected() {
+        assert!(matches!(
+            OutputPattern::new("(", true),
+            Err(crate::Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn batch_lines_get_their_own_seq() {
+        let pattern = OutputPattern::new("b", false).unwrap();
+        let found = pattern
+            .find(&batch(&["a", "b", "b"], &[4, 6, 9]), 1, StdioStream::Stdout)
+            .unwrap();
+        assert_eq!(found.seq, 6);
+        assert_eq!(found.line, "b");
+    }
+
+    #[test]
+    fn other_instances_and_streams_are_ignored() {
+        let pattern = OutputPattern::new("a", false).unwrap();
+        assert!(pattern
+            .find(&stdout(2, 1, "a"), 1, StdioStream::Stdout)
+            .is_none());
+        assert!(pattern
+            .find(&stdout(1, 1, "a"), 1, StdioStream::Stderr)
+            .is_none());
+    }
+}
@@ -1,5 +1,6 @@
 use serde::de::DeserializeOwned;
 use tauri::{
+    ipc::Channel,
     plugin::{PluginApi, PluginHandle},
     AppHandle, Runtime,
 };
@@ -30,13 +31,27 @@ impl<R: Runtime> Js<R> {
         ))
     }
 
-    pub async fn kill(&self, _name: String) -> crate::Result<()> {
+    pub async fn exec(
+        &self,
+        _config: SpawnConfig,
+        _options: ExecOptions,
+    ) -> crate::Result<ExecResult> {
         Err(crate::Error::InvalidConfig(
             "JS process management is not supported on mobile".to_string(),
         ))
     }
 
-    pub async fn kill_all(&self) -> crate::Result<()> {
+    pub async fn kill(
+        &self,
+        _name: String,
+        _instance_id: Option<u64>,
+    ) -> crate::Result<ShutdownResult> {
+        Err(crate::Error::InvalidConfig(
+            "JS process management is not supported on mobile".to_string(),
+        ))
+    }
+
+    pub async fn kill_all(&self) -> crate::Result<Vec<ShutdownResult>> {
         Err(crate::Error::InvalidConfig(
             "JS process management is not supported on mobile".to_string(),
         ))
@@ -46,6 +61,7 @@ impl<R: Runtime> Js<R> {
         &self,
         _name: String,
         _config: Option<SpawnConfig>,
+        _instance_id: Option<u64>,
     ) -> crate::Result<ProcessInfo> {
         Err(crate::Error::InvalidConfig(
             "JS process management is not supported on mobile".to_string(),
@@ -58,13 +74,125 @@ impl<R: Runtime> Js<R> {
         ))
     }
 
-    pub async fn get_status(&self, _name: String) -> crate::Result<ProcessInfo> {
+    pub async fn list_orphans(&self) -> crate::Result<Vec<OrphanInfo>> {
+        Err(crate::Error::InvalidConfig(
+            "JS process management is not supported on mobile".to_string(),
+        ))
+    }
+
+    pub async fn get_status(
+        &self,
+        _name: String,
+        _instance_id: Option<u64>,
+    ) -> crate::Result<ProcessInfo> {
+        Err(crate::Error::InvalidConfig(
+            "JS process management is not supported on mobile".to_string(),
+        ))
+    }
+
+    pub async fn wait(
+        &self,
+        _name: String,
+        _timeout_ms: Option<u64>,
+        _instance_id: Option<u64>,
+    ) -> crate::Result<ExitEventPayload> {
+        Err(crate::Error::InvalidConfig(
+            "JS process management is not supported on mobile".to_string(),
+        ))
+    }
+
+    pub async fn wait_for_output(
+        &self,
+        _name: String,
+        _pattern: String,
+        _options: WaitForOutputOptions,
+    ) -> crate::Result<OutputMatch> {
+        Err(crate::Error::InvalidConfig(
+            "JS process management is not supported on mobile".to_string(),
+        ))
+    }
+
+    pub async fn subscribe(
+        &self,
+        _name: String,
+        _webview: String,
+        _window: String,
+        _channel: Channel<ProcessEvent>,
+        _from_seq: Option<u64>,
+    ) -> crate::Result<u64> {
+        Err(crate::Error::InvalidConfig(
+            "JS process management is not supported on mobile".to_string(),
+        ))
+    }
+
+    pub async fn unsubscribe(&self, _subscription_id: u64) -> crate::Result<()> {
         Err(crate::Error::InvalidConfig(
             "JS process management is not supported on mobile".to_string(),
         ))
     }
 
-    pub async fn write_stdin(&self, _name: String, _data: String) -> crate::Result<()> {
+    pub fn unsubscribe_webview(&self, _webview: &str) {}
+
+    pub fn unsubscribe_window(&self, _window: &str) {}
+
+    pub async fn write_stdin(
+        &self,
+        _name: String,
+        _data: String,
+        _instance_id: Option<u64>,
+    ) -> crate::Result<()> {
+        Err(crate::Error::InvalidConfig(
+            "JS process management is not supported on mobile".to_string(),
+        ))
+    }
+
+    pub async fn write_stdin_bytes(
+        &self,
+        _name: String,
+        _data: Vec<u8>,
+        _instance_id: Option<u64>,
+    ) -> crate::Result<()> {
+        Err(crate::Error::InvalidConfig(
+            "JS process management is not supported on mobile".to_string(),
+        ))
+    }
+
+    pub async fn close_stdin(&self, _name: String, _instance_id: Option<u64>) -> crate::Result<()> {
+        Err(crate::Error::InvalidConfig(
+            "JS process management is not supported on mobile".to_string(),
+        ))
+    }
+
+    pub async fn write_ipc(
+        &self,
+        _name: String,
+        _data: String,
+        _instance_id: Option<u64>,
+        _connection_id: Option<u64>,
+    ) -> crate::Result<()> {
+        Err(crate::Error::InvalidConfig(
+            "JS process management is not supported on mobile".to_string(),
+        ))
+    }
+
+    pub async fn signal(
+        &self,
+        _name: String,
+        _signal: String,
+        _instance_id: Option<u64>,
+    ) -> crate::Result<()> {
+        Err(crate::Error::InvalidConfig(
+            "JS process management is not supported on mobile".to_string(),
+        ))
+    }
+
+    pub async fn resize(
+        &self,
+        _name: String,
+        _cols: u16,
+        _rows: u16,
+        _instance_id: Option<u64>,
+    ) -> crate::Result<()> {
         Err(crate::Error::InvalidConfig(
             "JS process management is not supported on mobile".to_string(),
         ))
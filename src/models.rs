@@ -1,7 +1,46 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Plugin configuration, read from `plugins.js` in the Tauri config.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginConfig {
+    /// What happens at startup to processes left running by a previous run of the app that
+    /// crashed or was killed (default "terminate")
+    pub orphans: Option<OrphanPolicy>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OrphanPolicy {
+    /// Stop them with SIGTERM, then SIGKILL if they are still running after 5 seconds
+    #[default]
+    Terminate,
+    /// Track them again as `Orphaned` processes, which can be inspected, signalled and killed
+    /// but whose output cannot be read
+    Reattach,
+    /// Leave them running and untracked
+    Ignore,
+}
+
+/// A process left running by a previous run of the app, found at startup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanInfo {
+    pub name: String,
+    pub pid: u32,
+    /// Unix timestamp in milliseconds at which the previous run spawned it
+    pub started_at: u64,
+    /// Program and arguments it was started with
+    pub command: Vec<String>,
+    pub cwd: Option<String>,
+    /// What was done with it
+    pub policy: OrphanPolicy,
+    /// Instance id it was reattached as, with the "reattach" policy
+    pub instance_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SpawnConfig {
     /// Runtime to use: "bun", "deno", or "node"
@@ -18,28 +57,632 @@ pub struct SpawnConfig {
     pub cwd: Option<String>,
     /// Environment variables
     pub env: Option<HashMap<String, String>>,
+    /// Graceful shutdown behaviour used by kill, kill_all, restart and app exit
+    pub shutdown: Option<ShutdownConfig>,
+    /// Automatic restart behaviour when the process exits on its own
+    pub restart_policy: Option<RestartPolicy>,
+    /// How stdout lines are decoded before being emitted (default "lossy")
+    pub stdout_encoding: Option<OutputEncoding>,
+    /// How stderr lines are decoded before being emitted (default "lossy")
+    pub stderr_encoding: Option<OutputEncoding>,
+    /// How stdout is split into messages; stdin writes are framed to match (default "newline").
+    /// stderr is always read line by line. With `ipc`, applies to the IPC pipes instead.
+    pub framing: Option<Framing>,
+    /// Opens a dedicated pipe pair for RPC on fds 3 (child reads) and 4 (child writes),
+    /// announced to the child as `TAURI_JS_IPC_FDS=3,4`. Unix only.
+    pub ipc: Option<bool>,
+    /// Listens on a private Unix socket whose path is passed to the child as `TAURI_JS_SOCKET`.
+    /// Every connection is a separate message stream framed with `framing`, and the socket
+    /// stays open until the process is stopped or replaced. Unix only.
+    pub socket: Option<bool>,
+    /// Whether the process's events, output and lifecycle alike, are emitted to every webview
+    /// (default true). Set to false to deliver them only to `subscribe` channels.
+    pub broadcast: Option<bool>,
+    /// Size of the buffer of recent output that `subscribe` can replay
+    pub replay: Option<ReplayConfig>,
+    /// Coalesces stdout and stderr lines into `js-process-output-batch` events
+    pub batch: Option<BatchConfig>,
+    /// Caps the stdout and stderr lines forwarded per second. IPC messages are never limited.
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Limits the writes waiting for stdin, the IPC pipe or a socket connection
+    pub write_queue: Option<WriteQueueConfig>,
+    /// Starts the process in its own process group, so kill, kill_all, restart and app exit
+    /// stop everything it spawned, not just the process itself. Unix only.
+    pub process_group: Option<bool>,
+    /// Has the kernel SIGKILL the process if the app dies without shutting it down, e.g. when
+    /// it crashes or is killed. Linux only, ignored elsewhere.
+    ///
+    /// Linux sends the signal when the *thread* that spawned the process exits, not only the
+    /// whole app. Spawns from the commands and from restarts run on the async runtime's worker
+    /// threads, which live as long as the app; call `app.js().spawn(...)` or `exec(...)` from
+    /// a thread that does too, not e.g. from a short-lived thread that blocks on the future.
+    pub kill_on_parent_death: Option<bool>,
+    /// Runs the process in a pseudo-terminal, for interactive runtimes and REPLs. stdout and
+    /// stderr arrive together as raw stdout chunks and stdin takes keystrokes. Unix only.
+    pub pty: Option<bool>,
+    /// Initial terminal size with `pty` (default 80x24)
+    pub pty_size: Option<PtySize>,
+    /// Probe that tells when the process is ready to serve requests. The process stays
+    /// "starting" until the probe succeeds, moving it to "ready", or gives up, moving it to
+    /// "running"
+    pub readiness: Option<ReadinessConfig>,
+}
+
+/// How a process's readiness is checked. Exactly one of `pattern`, `tcp_port`, `http_url`
+/// and `ping` must be set.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadinessConfig {
+    /// Ready once a line of stdout contains this
+    pub pattern: Option<String>,
+    /// Treats `pattern`, or the ping's `response`, as a regular expression
+    pub regex: Option<bool>,
+    /// Ready once a TCP connection to this port on localhost succeeds
+    pub tcp_port: Option<u16>,
+    /// Ready once a GET of this `http://` URL on localhost, 127.0.0.1 or [::1] returns 2xx
+    pub http_url: Option<String>,
+    /// Ready once the process answers a message on stdin
+    pub ping: Option<PingProbe>,
+    /// Gives up on the process becoming ready after this long (default 30000)
+    pub timeout_ms: Option<u64>,
+    /// Time between TCP, HTTP and ping attempts (default 250); a TCP or HTTP attempt that
+    /// takes longer, but at least 1s and at most 5s, fails
+    pub interval_ms: Option<u64>,
+    /// Makes spawn and restart resolve only once the process is ready, and fail if it does
+    /// not become ready, after stopping it
+    pub wait: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PingProbe {
+    /// Written to stdin as is, like `write_stdin`, every `interval_ms` until answered
+    pub message: String,
+    /// Expected in a line of stdout
+    pub response: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PtySize {
+    pub cols: u16,
+    pub rows: u16,
+}
+
+impl Default for PtySize {
+    fn default() -> Self {
+        Self { cols: 80, rows: 24 }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteQueueConfig {
+    /// Writes that can be waiting per pipe or connection (default 64)
+    pub max_pending: Option<usize>,
+    /// What a write does when the queue is full (default "wait")
+    pub when_full: Option<QueueFullPolicy>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QueueFullPolicy {
+    /// Wait until the child has read enough for the write to be queued
+    #[default]
+    Wait,
+    /// Fail the write right away
+    Error,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayConfig {
+    /// Most stdout, stderr and IPC messages kept, counting each line of a batch (default 1000,
+    /// 0 disables the buffer)
+    pub max_lines: Option<usize>,
+    /// Most bytes of message data kept (default 1048576)
+    pub max_bytes: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchConfig {
+    /// How long lines are collected before they are emitted (default 16)
+    pub interval_ms: Option<u64>,
+    /// Bytes of line data after which a batch is emitted early (default 65536)
+    pub max_bytes: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitConfig {
+    /// Most stdout and stderr lines forwarded per second
+    pub max_lines_per_sec: Option<u64>,
+    /// Most bytes of stdout and stderr forwarded per second
+    pub max_bytes_per_sec: Option<u64>,
+    /// What happens to lines over the limit (default "drop")
+    pub policy: Option<OverflowPolicy>,
+    /// With the "sample" policy, one in this many lines over the limit is forwarded (default 100)
+    pub sample_every: Option<u64>,
+    /// With the "spill" policy, size at which the spill file is rotated (default 16 MiB).
+    /// The previous file is kept with a `.1` suffix, replacing the one before it.
+    pub max_spill_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverflowPolicy {
+    /// Discard lines over the limit
+    #[default]
+    Drop,
+    /// Forward one in `sample_every` lines over the limit and discard the rest
+    Sample,
+    /// Append lines over the limit to a file in the app's log directory
+    Spill,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Framing {
+    /// Messages end with `\n`; stdin data is written as is
+    #[default]
+    Newline,
+    /// Messages end with a NUL byte; stdin writes get one appended
+    Nul,
+    /// Messages start with their length as a 4-byte big-endian integer; stdin writes get one
+    /// prepended
+    LengthPrefixed,
+    /// No framing: stdout is emitted in chunks as it arrives; stdin data is written as is
+    Raw,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputEncoding {
+    /// Decode as UTF-8, replacing invalid bytes with U+FFFD
+    #[default]
+    Lossy,
+    /// Decode as UTF-8; lines that are not valid UTF-8 are reported via
+    /// `js-process-decode-error` instead of being emitted
+    Strict,
+    /// Emit the raw bytes of each line base64-encoded
+    Base64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartMode {
+    /// Never restart automatically
+    #[default]
+    Never,
+    /// Restart when the process exits with a non-zero code or is killed by a signal
+    OnFailure,
+    /// Restart whenever the process exits, including clean exits
+    Always,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestartPolicy {
+    pub mode: RestartMode,
+    /// Restarts allowed within `window_ms` before the process is considered crash-looping (default 5)
+    pub max_retries: Option<u32>,
+    /// Sliding window used for crash-loop detection and backoff (default 60000)
+    pub window_ms: Option<u64>,
+    /// Delay before the first restart, doubled for each restart within the window (default 500)
+    pub initial_backoff_ms: Option<u64>,
+    /// Upper bound for the restart delay (default 30000)
+    pub max_backoff_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShutdownConfig {
+    /// Signal sent to ask the process to stop: "SIGTERM", "SIGINT", "SIGHUP" or "SIGQUIT".
+    /// Defaults to "SIGTERM", or to no signal once `message` has been written.
+    /// Ignored on Windows, where only stdin is used.
+    pub signal: Option<String>,
+    /// Message written to stdin before it is closed, e.g. a shutdown RPC request
+    pub message: Option<String>,
+    /// How long to wait for the process to exit before force-killing it, writing `message`
+    /// included (default 5000)
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShutdownOutcome {
+    /// The process exited on its own within the shutdown timeout
+    Graceful,
+    /// The process did not exit in time and was force-killed
+    Killed,
+    /// The process had already exited before the shutdown started
+    AlreadyExited,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShutdownResult {
+    pub name: String,
+    pub instance_id: u64,
+    pub outcome: ShutdownOutcome,
+    pub code: Option<i32>,
+    /// Signal that terminated the process (Unix only)
+    pub signal: Option<i32>,
+    pub elapsed_ms: u64,
+}
+
+/// Options for a one-shot run with `exec`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecOptions {
+    /// Kills the process if it is still running after this long
+    pub timeout_ms: Option<u64>,
+    /// Written to the process's stdin, which is then closed. Without it stdin is empty.
+    pub stdin: Option<String>,
+    /// Most bytes of stdout and of stderr kept; the rest is read and discarded (default
+    /// 1048576)
+    pub max_output_bytes: Option<usize>,
+}
+
+/// Outcome of a one-shot run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecResult {
+    pub code: Option<i32>,
+    /// Signal that terminated the process (Unix only)
+    pub signal: Option<i32>,
+    /// UTF-8 with invalid bytes replaced, or base64 with `stdoutEncoding: "base64"`
+    pub stdout: String,
+    /// Like `stdout`, according to `stderrEncoding`
+    pub stderr: String,
+    /// Whether stdout went over `maxOutputBytes`
+    pub stdout_truncated: bool,
+    /// Whether stderr went over `maxOutputBytes`
+    pub stderr_truncated: bool,
+    /// Whether the process was killed for running past `timeoutMs`
+    pub timed_out: bool,
+    pub duration_ms: u64,
+}
+
+/// Options for `wait_for_output`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WaitForOutputOptions {
+    /// Stream to watch (default stdout)
+    pub stream: Option<StdioStream>,
+    /// Treats the pattern as a regular expression instead of a plain substring
+    pub regex: Option<bool>,
+    /// Gives up once this long has passed, leaving the process running
+    pub timeout_ms: Option<u64>,
+    /// Instance to watch; defaults to the current one
+    pub instance_id: Option<u64>,
+}
+
+/// A line of output that matched a pattern.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputMatch {
+    pub name: String,
+    pub instance_id: u64,
+    /// Position of the line in the process's output, see [`StdioEventPayload::seq`]
+    pub seq: u64,
+    pub stream: StdioStream,
+    /// Unix timestamp in milliseconds at which the line was read from the process
+    pub captured_at: u64,
+    pub line: String,
+    /// The whole match followed by the regex's capture groups, `None` for groups that did
+    /// not take part; only the whole match for a substring
+    pub captures: Vec<Option<String>>,
+    /// Named capture groups that took part in the match
+    pub named_captures: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProcessState {
+    /// Spawned, but not yet running, or waiting for its readiness probe
+    Starting,
+    /// Running and confirmed ready to serve requests
+    Ready,
+    /// Running
+    Running,
+    /// Paused with SIGSTOP until it is sent SIGCONT
+    Suspended,
+    /// Left running by a previous run of the app and reattached at startup; its output
+    /// cannot be read
+    Orphaned,
+    /// Being shut down via kill, kill_all or restart
+    Stopping,
+    /// Exited cleanly or was stopped on request
+    Exited,
+    /// Crashed, gave up restarting, or could not be restarted
+    Failed,
+    /// Exited and waiting to be started again
+    Restarting,
+}
+
+impl ProcessState {
+    /// Whether the child process is still alive in this state.
+    pub fn is_alive(self) -> bool {
+        matches!(
+            self,
+            Self::Starting
+                | Self::Ready
+                | Self::Running
+                | Self::Suspended
+                | Self::Orphaned
+                | Self::Stopping
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProcessInfo {
     pub name: String,
+    /// Unique per spawned instance, so a restarted process can be told apart from the old one
+    pub instance_id: u64,
     pub pid: Option<u32>,
+    pub state: ProcessState,
+    /// Whether the process is still alive, see [`ProcessState::is_alive`]
     pub running: bool,
+    /// Unix timestamp in milliseconds
+    pub started_at: u64,
+    /// Unix timestamp in milliseconds
+    pub exited_at: Option<u64>,
+    pub exit_code: Option<i32>,
+    /// Signal that terminated the process (Unix only)
+    pub exit_signal: Option<i32>,
+    pub exit_reason: Option<ExitReason>,
+    /// Automatic restarts since the process was last spawned explicitly
+    pub restart_count: u32,
+    /// Config this instance was spawned with
+    pub config: SpawnConfig,
+    /// Output held back by the rate limit
+    pub output_stats: OutputStats,
+}
+
+/// Stdout and stderr lines held back by `SpawnConfig::rate_limit` since the instance started.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputStats {
+    /// Lines discarded by the "drop" and "sample" policies
+    pub dropped_lines: u64,
+    pub dropped_bytes: u64,
+    /// Lines written to the spill file instead of being emitted
+    pub spilled_lines: u64,
+    pub spilled_bytes: u64,
+    /// File lines are spilled to, once the "spill" policy kicked in
+    pub spill_path: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StdioEventPayload {
     pub name: String,
+    pub instance_id: u64,
+    /// Position in the process's output across stdout, stderr and IPC, counting across
+    /// restarts, so streams can be merged in order and gaps detected. Assigned when the line
+    /// is read, so lines the rate limit held back leave a gap
+    pub seq: u64,
+    pub stream: StdioStream,
+    /// Unix timestamp in milliseconds at which the line was read from the process
+    pub captured_at: u64,
+    pub data: String,
+}
+
+/// A message received over a process's IPC pipe or socket.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IpcEventPayload {
+    pub name: String,
+    pub instance_id: u64,
+    /// The socket connection the message arrived on; `None` for the IPC pipe
+    pub connection_id: Option<u64>,
+    /// Position in the process's output, see [`StdioEventPayload::seq`]
+    pub seq: u64,
+    /// Always [`StdioStream::Ipc`]
+    pub stream: StdioStream,
+    /// Unix timestamp in milliseconds at which the message was read from the process
+    pub captured_at: u64,
+    pub data: String,
+}
+
+/// Stdout or stderr lines coalesced by `SpawnConfig::batch`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputBatchEventPayload {
+    pub name: String,
+    pub instance_id: u64,
+    /// Position of the first line in the process's output, see [`StdioEventPayload::seq`]
+    pub seq: u64,
+    /// [`StdioStream::Stdout`] or [`StdioStream::Stderr`]
+    pub stream: StdioStream,
+    /// Unix timestamp in milliseconds at which the first line was read from the process
+    pub captured_at: u64,
+    pub lines: Vec<String>,
+    /// Position of each line; not consecutive when output of other streams was read in
+    /// between or lines were held back by the rate limit
+    pub seqs: Vec<u64>,
+}
+
+/// Outcome of a process's readiness probe.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadinessEventPayload {
+    pub name: String,
+    pub instance_id: u64,
+    /// Time between spawn and the probe succeeding or giving up
+    pub elapsed_ms: u64,
+    /// Why the process did not become ready; `None` once it is ready
+    pub error: Option<String>,
+}
+
+/// Reported while the rate limit is holding back output, at most once per second.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThrottleEventPayload {
+    pub name: String,
+    pub instance_id: u64,
+    pub policy: OverflowPolicy,
+    /// Totals since the instance started
+    pub stats: OutputStats,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionEventPayload {
+    pub name: String,
+    pub instance_id: u64,
+    pub connection_id: u64,
+    /// `false` once the connection has closed
+    pub connected: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StdioStream {
+    Stdout,
+    Stderr,
+    Ipc,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodeErrorEventPayload {
+    pub name: String,
+    pub instance_id: u64,
+    pub stream: StdioStream,
+    pub error: String,
+    /// The offending line, decoded lossily; empty for a skipped frame
     pub data: String,
 }
 
+/// Event delivered to a `subscribe` channel, as `{ event, data }`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", content = "data", rename_all = "camelCase")]
+pub enum ProcessEvent {
+    Stdout(StdioEventPayload),
+    Stderr(StdioEventPayload),
+    Ipc(IpcEventPayload),
+    Batch(OutputBatchEventPayload),
+    Ready(ReadinessEventPayload),
+    ReadinessFailed(ReadinessEventPayload),
+    Exit(ExitEventPayload),
+    DecodeError(DecodeErrorEventPayload),
+    Throttled(ThrottleEventPayload),
+    Connection(ConnectionEventPayload),
+    Restart(RestartEventPayload),
+    CrashLoop(CrashLoopEventPayload),
+}
+
+impl ProcessEvent {
+    /// Name of the process the event belongs to.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Stdout(payload) | Self::Stderr(payload) => &payload.name,
+            Self::Ipc(payload) => &payload.name,
+            Self::Batch(payload) => &payload.name,
+            Self::Ready(payload) | Self::ReadinessFailed(payload) => &payload.name,
+            Self::Exit(payload) => &payload.name,
+            Self::DecodeError(payload) => &payload.name,
+            Self::Throttled(payload) => &payload.name,
+            Self::Connection(payload) => &payload.name,
+            Self::Restart(payload) => &payload.name,
+            Self::CrashLoop(payload) => &payload.name,
+        }
+    }
+
+    /// Sequence number of an output event, the first line's for batches; `None` for
+    /// lifecycle events such as readiness and exits.
+    pub fn seq(&self) -> Option<u64> {
+        match self {
+            Self::Stdout(payload) | Self::Stderr(payload) => Some(payload.seq),
+            Self::Ipc(payload) => Some(payload.seq),
+            Self::Batch(payload) => Some(payload.seq),
+            _ => None,
+        }
+    }
+
+    /// Sequence number of an output event's last line.
+    pub(crate) fn last_seq(&self) -> Option<u64> {
+        match self {
+            Self::Batch(payload) => payload.seqs.last().copied(),
+            _ => self.seq(),
+        }
+    }
+
+    /// Number of lines in an output event.
+    pub(crate) fn line_count(&self) -> usize {
+        match self {
+            Self::Stdout(_) | Self::Stderr(_) | Self::Ipc(_) => 1,
+            Self::Batch(payload) => payload.lines.len(),
+            _ => 0,
+        }
+    }
+
+    /// Size of an output event's data, as counted against the replay buffer.
+    pub(crate) fn data_len(&self) -> usize {
+        match self {
+            Self::Stdout(payload) | Self::Stderr(payload) => payload.data.len(),
+            Self::Ipc(payload) => payload.data.len(),
+            Self::Batch(payload) => payload.lines.iter().map(String::len).sum(),
+            _ => 0,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExitEventPayload {
     pub name: String,
+    pub instance_id: u64,
+    pub code: Option<i32>,
+    /// Signal that terminated the process (Unix only)
+    pub signal: Option<i32>,
+    pub reason: ExitReason,
+    /// Time between spawn and exit
+    pub uptime_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExitReason {
+    /// Exited on its own with code 0
+    Exited,
+    /// Stopped via kill, kill_all or app exit
+    KilledByRequest,
+    /// Exited on its own with a non-zero code or was killed by a signal
+    Crashed,
+    /// Stopped by restart to make way for a new instance
+    Restart,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestartEventPayload {
+    pub name: String,
+    /// The instance that exited
+    pub instance_id: u64,
+    /// Exit code of the instance being replaced
+    pub code: Option<i32>,
+    /// Number of automatic restarts since the process was last spawned explicitly
+    pub attempt: u32,
+    pub delay_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashLoopEventPayload {
+    pub name: String,
+    /// The instance that exited
+    pub instance_id: u64,
+    /// Exit code of the last instance
     pub code: Option<i32>,
+    /// Restarts that happened within the window
+    pub restarts: u32,
+    pub window_ms: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,255 @@
+//! Pseudo-terminals for interactive runtimes and REPLs, which turn off colors and line
+//! editing and buffer their output when they are not attached to a terminal.
+
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::process::Command;
+
+#[cfg(unix)]
+use crate::ipc::cvt;
+use crate::models::PtySize;
+
+pub(crate) type PtyReader = Box<dyn AsyncRead + Send + Unpin>;
+pub(crate) type PtyWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// A pseudo-terminal set up for a command that has not been spawned yet.
+#[cfg(unix)]
+pub(crate) struct PendingPty {
+    /// The child's end, closed in the parent once the child holds its copies
+    slave: std::os::fd::OwnedFd,
+    master: PtyMaster,
+}
+
+#[cfg(not(unix))]
+pub(crate) enum PendingPty {}
+
+/// Parent side of a pseudo-terminal, shared by its reader, its writer and the process entry,
+/// which resizes it.
+#[cfg(unix)]
+#[derive(Clone)]
+pub(crate) struct PtyMaster(std::sync::Arc<tokio::io::unix::AsyncFd<std::os::fd::OwnedFd>>);
+
+#[cfg(not(unix))]
+#[derive(Clone)]
+pub(crate) enum PtyMaster {}
+
+/// Opens a pseudo-terminal of `size` and makes its other end the child's controlling
+/// terminal and stdio, in a session of its own.
+#[cfg(unix)]
+pub(crate) fn attach(cmd: &mut Command, size: PtySize) -> io::Result<PendingPty> {
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::fs::OpenOptionsExt;
+
+    // SAFETY: plain calls on a descriptor that posix_openpt() returned and nobody else owns
+    let master = unsafe {
+        let fd = cvt(libc::posix_openpt(
+            libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC,
+        ))?;
+        let master = OwnedFd::from_raw_fd(fd);
+        cvt(libc::grantpt(fd))?;
+        cvt(libc::unlockpt(fd))?;
+        let flags = cvt(libc::fcntl(fd, libc::F_GETFL))?;
+        cvt(libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK))?;
+        master
+    };
+    let slave = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NOCTTY)
+        .open(slave_path(master.as_raw_fd())?)?;
+    let slave = OwnedFd::from(slave);
+    let master = PtyMaster(std::sync::Arc::new(tokio::io::unix::AsyncFd::new(master)?));
+    master.resize(size)?;
+
+    let slave_fd = slave.as_raw_fd();
+    cmd.stdin(std::process::Stdio::null());
+    cmd.stdout(std::process::Stdio::null());
+    cmd.stderr(std::process::Stdio::null());
+    // SAFETY: the closure only calls async-signal-safe functions
+    unsafe {
+        cmd.pre_exec(move || {
+            cvt(libc::setsid())?;
+            cvt(libc::ioctl(slave_fd, libc::TIOCSCTTY as _, 0))?;
+            // The slave is close-on-exec; dup2 clears the flag on the targets
+            for fd in 0..=2 {
+                cvt(libc::dup2(slave_fd, fd))?;
+            }
+            Ok(())
+        });
+    }
+
+    Ok(PendingPty { slave, master })
+}
+
+#[cfg(not(unix))]
+pub(crate) fn attach(_cmd: &mut Command, _size: PtySize) -> io::Result<PendingPty> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "pseudo-terminals are only supported on Unix",
+    ))
+}
+
+impl PendingPty {
+    /// Closes the child's end once it has been spawned, so reading ends when the child and
+    /// everything it started have closed the terminal, and returns the parent's.
+    #[cfg(unix)]
+    pub(crate) fn finish(self) -> (PtyReader, PtyWriter, PtyMaster) {
+        drop(self.slave);
+        (
+            Box::new(self.master.clone()),
+            Box::new(self.master.clone()),
+            self.master,
+        )
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn finish(self) -> (PtyReader, PtyWriter, PtyMaster) {
+        match self {}
+    }
+}
+
+impl PtyMaster {
+    /// Sets the terminal size; the kernel sends SIGWINCH to the child's foreground process
+    /// group.
+    #[cfg(unix)]
+    pub(crate) fn resize(&self, size: PtySize) -> io::Result<()> {
+        use std::os::fd::AsRawFd;
+
+        let winsize = libc::winsize {
+            ws_row: size.rows,
+            ws_col: size.cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        // SAFETY: `winsize` outlives the call and the descriptor is open
+        cvt(unsafe {
+            libc::ioctl(
+                self.0.as_raw_fd(),
+                libc::TIOCSWINSZ,
+                &winsize as *const libc::winsize,
+            )
+        })?;
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn resize(&self, _size: PtySize) -> io::Result<()> {
+        match *self {}
+    }
+}
+
+#[cfg(unix)]
+impl AsyncRead for PtyMaster {
+    fn poll_read(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        use std::os::fd::AsRawFd;
+
+        loop {
+            let mut guard = std::task::ready!(self.0.poll_read_ready(cx))?;
+            let unfilled = buf.initialize_unfilled();
+            let read = guard.try_io(|fd| {
+                // SAFETY: `unfilled` is a writable buffer of the given length
+                let n = unsafe {
+                    libc::read(fd.as_raw_fd(), unfilled.as_mut_ptr().cast(), unfilled.len())
+                };
+                if n == -1 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(n as usize)
+                }
+            });
+            match read {
+                Ok(Ok(n)) => {
+                    buf.advance(n);
+                    return std::task::Poll::Ready(Ok(()));
+                }
+                // Linux reports EIO instead of EOF once every copy of the child's end is closed
+                Ok(Err(e)) if e.raw_os_error() == Some(libc::EIO) => {
+                    return std::task::Poll::Ready(Ok(()));
+                }
+                Ok(Err(e)) => return std::task::Poll::Ready(Err(e)),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl AsyncWrite for PtyMaster {
+    fn poll_write(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<io::Result<usize>> {
+        use std::os::fd::AsRawFd;
+
+        loop {
+            let mut guard = std::task::ready!(self.0.poll_write_ready(cx))?;
+            let written = guard.try_io(|fd| {
+                // SAFETY: `buf` is readable for the given length
+                let n = unsafe { libc::write(fd.as_raw_fd(), buf.as_ptr().cast(), buf.len()) };
+                if n == -1 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(n as usize)
+                }
+            });
+            match written {
+                Ok(result) => return std::task::Poll::Ready(result),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+/// Path of the child's end of the pseudo-terminal whose parent side is `master`.
+#[cfg(target_os = "linux")]
+fn slave_path(master: libc::c_int) -> io::Result<std::path::PathBuf> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut buf = [0 as libc::c_char; 128];
+    // SAFETY: `buf` has room for the given length
+    let ret = unsafe { libc::ptsname_r(master, buf.as_mut_ptr(), buf.len()) };
+    if ret != 0 {
+        return Err(io::Error::from_raw_os_error(ret));
+    }
+    // SAFETY: ptsname_r() succeeded, so `buf` holds a NUL-terminated string
+    let name = unsafe { std::ffi::CStr::from_ptr(buf.as_ptr()) };
+    Ok(std::ffi::OsStr::from_bytes(name.to_bytes()).into())
+}
+
+/// Path of the child's end of the pseudo-terminal whose parent side is `master`.
+#[cfg(all(unix, not(target_os = "linux")))]
+fn slave_path(master: libc::c_int) -> io::Result<std::path::PathBuf> {
+    use std::os::unix::ffi::OsStrExt;
+
+    // ptsname() returns a static buffer, so calls must not overlap
+    static PTSNAME: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    let _guard = PTSNAME.lock().unwrap_or_else(|e| e.into_inner());
+    // SAFETY: the result is copied out before the lock is released
+    let name = unsafe { libc::ptsname(master) };
+    if name.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: a non-null result is a NUL-terminated string
+    let name = unsafe { std::ffi::CStr::from_ptr(name) };
+    Ok(std::ffi::OsStr::from_bytes(name.to_bytes()).into())
+}
@@ -0,0 +1,216 @@
+//! Readiness probes, which tell when a process has started serving rather than merely
+//! started running.
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use crate::matcher::OutputPattern;
+use crate::models::ReadinessConfig;
+
+/// Longest HTTP status line read from a probed server.
+const MAX_STATUS_LINE: u64 = 1024;
+
+pub(crate) enum Probe {
+    /// A line of stdout matching the pattern
+    Output(OutputPattern),
+    /// A TCP connection to the port on localhost
+    Tcp(u16),
+    /// A 2xx response to a GET
+    Http(HttpTarget),
+    /// A line of stdout matching `response` after `message` was written to stdin
+    Ping {
+        message: String,
+        response: OutputPattern,
+    },
+}
+
+impl Probe {
+    /// Checks that `config` sets exactly one probe and that its pattern or URL is valid.
+    pub(crate) fn new(config: &ReadinessConfig) -> crate::Result<Self> {
+        let probes = [
+            config.pattern.is_some(),
+            config.tcp_port.is_some(),
+            config.http_url.is_some(),
+            config.ping.is_some(),
+        ];
+        if probes.iter().filter(|&&set| set).count() != 1 {
+            return Err(crate::Error::InvalidConfig(
+                "readiness needs exactly one of 'pattern', 'tcpPort', 'httpUrl' or 'ping'"
+                    .to_string(),
+            ));
+        }
+        let regex = config.regex.unwrap_or(false);
+        if let Some(ref pattern) = config.pattern {
+            Ok(Self::Output(OutputPattern::new(pattern, regex)?))
+        } else if let Some(port) = config.tcp_port {
+            Ok(Self::Tcp(port))
+        } else if let Some(ref url) = config.http_url {
+            Ok(Self::Http(HttpTarget::parse(url)?))
+        } else if let Some(ref ping) = config.ping {
+            Ok(Self::Ping {
+                message: ping.message.clone(),
+                response: OutputPattern::new(&ping.response, regex)?,
+            })
+        } else {
+            unreachable!("exactly one probe is set")
+        }
+    }
+}
+
+/// Where an HTTP probe sends its requests.
+pub(crate) struct HttpTarget {
+    host: String,
+    port: u16,
+    /// Host and port as written in the URL, for the `Host` header
+    authority: String,
+    path: String,
+}
+
+impl HttpTarget {
+    /// Accepts `http://` URLs on the local machine only; the probe speaks plain HTTP/1.1.
+    fn parse(url: &str) -> crate::Result<Self> {
+        let invalid = || {
+            crate::Error::InvalidConfig(format!(
+                "'httpUrl' must be an http:// URL on localhost, 127.0.0.1 or [::1]: {}",
+                url
+            ))
+        };
+        let rest = url.strip_prefix("http://").ok_or_else(invalid)?;
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(i) if rest[i..].starts_with('?') => (&rest[..i], format!("/{}", &rest[i..])),
+            Some(i) => (&rest[..i], rest[i..].to_string()),
+            None => (rest, "/".to_string()),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => {
+                (host, port.parse::<u16>().map_err(|_| invalid())?)
+            }
+            _ => (authority, 80),
+        };
+        let host = match host {
+            "localhost" | "127.0.0.1" => host,
+            "[::1]" => "::1",
+            _ => return Err(invalid()),
+        };
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            authority: authority.to_string(),
+            path,
+        })
+    }
+}
+
+/// Whether something accepts connections on `port` of localhost.
+pub(crate) async fn tcp_ready(port: u16) -> bool {
+    TcpStream::connect(("localhost", port)).await.is_ok()
+}
+
+/// Whether a GET of `target` gets a 2xx response.
+pub(crate) async fn http_ready(target: &HttpTarget) -> bool {
+    let Ok(mut stream) = TcpStream::connect((target.host.as_str(), target.port)).await else {
+        return false;
+    };
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        target.path, target.authority
+    );
+    if stream.write_all(request.as_bytes()).await.is_err() {
+        return false;
+    }
+    let mut status_line = String::new();
+    let mut reader = BufReader::new(stream).take(MAX_STATUS_LINE);
+    if reader.read_line(&mut status_line).await.is_err() {
+        return false;
+    }
+    // e.g. "HTTP/1.1 204 No Content"
+    let mut parts = status_line.split_whitespace();
+    parts
+        .next()
+        .is_some_and(|version| version.starts_with("HTTP/"))
+        && parts
+            .next()
+            .is_some_and(|code| code.len() == 3 && code.starts_with('2'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(url: &str) -> (String, u16, String, String) {
+        let target = HttpTarget::parse(url).unwrap();
+        (target.host, target.port, target.authority, target.path)
+    }
+
+    #[test]
+    fn http_url_defaults_to_port_80_and_root() {
+        assert_eq!(
+            parse("http://localhost"),
+            ("localhost".into(), 80, "localhost".into(), "/".into())
+        );
+        assert_eq!(
+            parse("http://127.0.0.1:3000/health?full=1"),
+            (
+                "127.0.0.1".into(),
+                3000,
+                "127.0.0.1:3000".into(),
+                "/health?full=1".into()
+            )
+        );
+    }
+
+    #[test]
+    fn http_url_with_ipv6_loopback() {
+        assert_eq!(
+            parse("http://[::1]"),
+            ("::1".into(), 80, "[::1]".into(), "/".into())
+        );
+        assert_eq!(
+            parse("http://[::1]:8080/"),
+            ("::1".into(), 8080, "[::1]:8080".into(), "/".into())
+        );
+    }
+
+    #[test]
+    fn http_url_with_query_only() {
+        assert_eq!(
+            parse("http://localhost:3000?ready"),
+            (
+                "localhost".into(),
+                3000,
+                "localhost:3000".into(),
+                "/?ready".into()
+            )
+        );
+    }
+
+    #[test]
+    fn http_url_must_be_local_http() {
+        for url in [
+            "https://localhost/",
+            "http://example.com/",
+            "http://localhost:http/",
+            "http://localhost:70000/",
+            "http://[::2]/",
+            "localhost:3000",
+        ] {
+            assert!(HttpTarget::parse(url).is_err(), "{url}");
+        }
+    }
+
+    #[test]
+    fn probe_needs_exactly_one_check() {
+        assert!(Probe::new(&ReadinessConfig::default()).is_err());
+        let both = ReadinessConfig {
+            pattern: Some("ready".into()),
+            tcp_port: Some(3000),
+            ..Default::default()
+        };
+        assert!(Probe::new(&both).is_err());
+        let tcp = ReadinessConfig {
+            tcp_port: Some(3000),
+            ..Default::default()
+        };
+        assert!(matches!(Probe::new(&tcp), Ok(Probe::Tcp(3000))));
+    }
+}
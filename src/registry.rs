@@ -0,0 +1,343 @@
+//! On-disk record of the processes each run of the app has spawned, so processes left behind
+//! by a run that crashed or was killed can be found at the next startup. Each run writes its
+//! own file, named after its pid, so instances of the app running side by side never claim
+//! each other's processes.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+/// A process spawned by some run of the app.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RegistryRecord {
+    pub(crate) name: String,
+    pub(crate) instance_id: u64,
+    pub(crate) pid: u32,
+    /// Tells the process apart from a later one that reuses its pid, see [`start_time`]
+    pub(crate) start_time: String,
+    pub(crate) started_at: u64,
+    pub(crate) command: Vec<String>,
+    pub(crate) cwd: Option<String>,
+    pub(crate) process_group: bool,
+}
+
+impl RegistryRecord {
+    /// Whether the pid still belongs to this process and it has not exited.
+    pub(crate) fn is_running(&self) -> bool {
+        start_time(self.pid).is_some_and(|t| t == self.start_time)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RegistryFile {
+    app_pid: u32,
+    app_start_time: String,
+    processes: Vec<RegistryRecord>,
+}
+
+pub(crate) struct Registry {
+    /// `None` when there is no app data dir or process start times cannot be read on this
+    /// platform, in which case nothing is recorded
+    file: Option<PathBuf>,
+    app_start_time: String,
+    records: Mutex<Vec<RegistryRecord>>,
+}
+
+impl Registry {
+    /// Opens this run's registry in `dir` and returns the processes of previous runs that are
+    /// still running. The files of those runs are removed; files of runs that are still going
+    /// are left alone.
+    pub(crate) fn open(dir: Option<PathBuf>) -> (Self, Vec<RegistryRecord>) {
+        let app_pid = std::process::id();
+        let (Some(dir), Some(app_start_time)) = (dir, start_time(app_pid)) else {
+            let registry = Self {
+                file: None,
+                app_start_time: String::new(),
+                records: Mutex::new(Vec::new()),
+            };
+            return (registry, Vec::new());
+        };
+
+        let mut orphans = Vec::new();
+        for entry in std::fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path.extension().map_or(true, |ext| ext != "json") {
+                continue;
+            }
+            let file = match std::fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| {
+                    serde_json::from_slice::<RegistryFile>(&bytes).map_err(|e| e.to_string())
+                }) {
+                Ok(file) => file,
+                Err(e) => {
+                    log::warn!("could not read {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            if start_time(file.app_pid).is_some_and(|t| t == file.app_start_time) {
+                continue;
+            }
+            orphans.extend(
+                file.processes
+                    .into_iter()
+                    .filter(RegistryRecord::is_running),
+            );
+            if let Err(e) = std::fs::remove_file(&path) {
+                log::warn!("could not remove {}: {}", path.display(), e);
+            }
+        }
+
+        let registry = Self {
+            file: Some(dir.join(format!("{}.json", app_pid))),
+            app_start_time,
+            records: Mutex::new(Vec::new()),
+        };
+        (registry, orphans)
+    }
+
+    pub(crate) fn add(&self, record: RegistryRecord) {
+        if self.file.is_none() {
+            return;
+        }
+        let mut records = self.records.lock().unwrap();
+        records.push(record);
+        self.save(&records);
+    }
+
+    /// Forgets a process once it has exited. Matching on the start time as well keeps a
+    /// newer process that was given the same pid.
+    pub(crate) fn remove(&self, pid: u32, start_time: &str) {
+        let mut records = self.records.lock().unwrap();
+        let len = records.len();
+        records.retain(|r| r.pid != pid || r.start_time != start_time);
+        if records.len() != len {
+            self.save(&records);
+        }
+    }
+
+    /// Replaces the file with a new one, so a crash mid-write never leaves it truncated.
+    fn save(&self, records: &[RegistryRecord]) {
+        let Some(path) = &self.file else {
+            return;
+        };
+        let result = (|| {
+            if records.is_empty() {
+                return match std::fs::remove_file(path) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                    _ => Ok(()),
+                };
+            }
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let file = RegistryFile {
+                app_pid: std::process::id(),
+                app_start_time: self.app_start_time.clone(),
+                processes: records.to_vec(),
+            };
+            let tmp = path.with_extension("json.tmp");
+            std::fs::write(&tmp, serde_json::to_vec(&file)?)?;
+            std::fs::rename(&tmp, path)
+        })();
+        if let Err(e) = result {
+            log::warn!("could not update {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// When a live process started, in a form that tells it apart from any other process that
+/// has had or will have the same pid. `None` if there is no such process, it has exited but
+/// not been reaped yet, or start times are not available on this platform.
+#[cfg(target_os = "linux")]
+pub(crate) fn start_time(pid: u32) -> Option<String> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let ticks = start_ticks(&stat)?;
+    let boot_id = std::fs::read_to_string("/proc/sys/kernel/random/boot_id").ok()?;
+    Some(format!("{}/{}", boot_id.trim(), ticks))
+}
+
+/// The start time field of a `/proc/<pid>/stat` line, or `None` for a zombie. It is in clock
+/// ticks since boot, so the boot id is needed to compare it across boots.
+#[cfg(target_os = "linux")]
+fn start_ticks(stat: &str) -> Option<&str> {
+    // The command name can contain spaces and parentheses; the fields after it cannot
+    let mut fields = stat.get(stat.rfind(')')? + 2..)?.split(' ');
+    if fields.next()? == "Z" {
+        return None;
+    }
+    // Field 22; the state was field 3
+    fields.nth(18)
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn start_time(pid: u32) -> Option<String> {
+    // SAFETY: `proc_bsdinfo` is plain data, for which all zeroes is a valid value
+    let mut info: libc::proc_bsdinfo = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<libc::proc_bsdinfo>() as libc::c_int;
+    // SAFETY: `info` is a writable buffer of `size` bytes
+    let written = unsafe {
+        libc::proc_pidinfo(
+            pid as libc::c_int,
+            libc::PROC_PIDTBSDINFO,
+            0,
+            &mut info as *mut libc::proc_bsdinfo as *mut libc::c_void,
+            size,
+        )
+    };
+    if written != size || info.pbi_status == libc::SZOMB {
+        return None;
+    }
+    Some(format!(
+        "{}.{:06}",
+        info.pbi_start_tvsec, info.pbi_start_tvusec
+    ))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub(crate) fn start_time(_pid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    /// A stat line with `comm` as the command name and `state` as the state, whose start
+    /// time is 4242.
+    fn stat(comm: &str, state: &str) -> String {
+        format!(
+            "1234 ({}) {} 1 1234 1234 0 -1 4194560 100 0 0 0 1 2 0 0 20 0 1 0 4242 1000 50",
+            comm, state
+        )
+    }
+
+    #[test]
+    fn start_ticks_reads_field_22() {
+        assert_eq!(start_ticks(&stat("node", "S")), Some("4242"));
+        assert_eq!(start_ticks(&stat("my server", "R")), Some("4242"));
+        assert_eq!(start_ticks(&stat("a) (b", "S")), Some("4242"));
+        assert_eq!(start_ticks(&stat("x) S 1 2 3", "S")), Some("4242"));
+    }
+
+    #[test]
+    fn start_ticks_skips_zombies_and_bad_lines() {
+        assert_eq!(start_ticks(&stat("node", "Z")), None);
+        assert_eq!(start_ticks("1234 (node) S 1 1234"), None);
+        assert_eq!(start_ticks("1234 (node"), None);
+        assert_eq!(start_ticks("1234 (node)"), None);
+        assert_eq!(start_ticks(""), None);
+    }
+
+    #[test]
+    fn start_time_tells_live_processes_apart() {
+        let own = start_time(std::process::id()).unwrap();
+        let parent = start_time(std::os::unix::process::parent_id()).unwrap();
+        assert_ne!(own, parent);
+        assert_eq!(start_time(std::process::id()), Some(own));
+        assert_eq!(start_time(u32::MAX), None);
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "tauri-plugin-js-registry-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn record(name: &str, pid: u32, start_time: &str) -> RegistryRecord {
+        RegistryRecord {
+            name: name.into(),
+            instance_id: 1,
+            pid,
+            start_time: start_time.into(),
+            started_at: 0,
+            command: vec!["node".into(), "server.js".into()],
+            cwd: None,
+            process_group: false,
+        }
+    }
+
+    fn write_run(path: &Path, app_pid: u32, app_start_time: &str, processes: &[RegistryRecord]) {
+        let file = RegistryFile {
+            app_pid,
+            app_start_time: app_start_time.into(),
+            processes: processes.to_vec(),
+        };
+        std::fs::write(path, serde_json::to_vec(&file).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn open_returns_running_processes_of_dead_runs() {
+        let dir = temp_dir("open");
+        let own_pid = std::process::id();
+        let own_start = start_time(own_pid).unwrap();
+        let parent_pid = std::os::unix::process::parent_id();
+
+        // A run with the right pid but another start time has exited, and its pid was reused
+        let dead = dir.join("dead.json");
+        write_run(
+            &dead,
+            own_pid,
+            "0/0",
+            &[
+                record("alive", own_pid, &own_start),
+                record("reused", own_pid, "0/0"),
+                record("gone", u32::MAX, "0/0"),
+            ],
+        );
+        let live = dir.join("live.json");
+        write_run(
+            &live,
+            parent_pid,
+            &start_time(parent_pid).unwrap(),
+            &[record("theirs", parent_pid, "0/0")],
+        );
+        std::fs::write(dir.join("broken.json"), "{").unwrap();
+        std::fs::write(dir.join("notes.txt"), "{}").unwrap();
+
+        let (_registry, orphans) = Registry::open(Some(dir.clone()));
+        let names: Vec<_> = orphans.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["alive"]);
+        assert!(!dead.exists());
+        assert!(live.exists());
+        assert!(dir.join("broken.json").exists());
+        assert!(dir.join("notes.txt").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn records_are_written_until_the_last_one_is_removed() {
+        let dir = temp_dir("records");
+        let (registry, orphans) = Registry::open(Some(dir.clone()));
+        assert!(orphans.is_empty());
+        let path = dir.join(format!("{}.json", std::process::id()));
+        assert!(!path.exists());
+
+        registry.add(record("a", 10, "1/1"));
+        registry.add(record("b", 11, "1/2"));
+        let file: RegistryFile = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(file.app_pid, std::process::id());
+        assert_eq!(file.processes.len(), 2);
+
+        // A different start time is a different process
+        registry.remove(10, "1/9");
+        registry.remove(10, "1/1");
+        let file: RegistryFile = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        let names: Vec<_> = file.processes.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["b"]);
+
+        registry.remove(11, "1/2");
+        assert!(!path.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,561 @@
+//! Per-webview subscriptions to a process's events, delivered over `tauri::ipc::Channel`s
+//! instead of being broadcast to every webview, and the replay buffers that let late
+//! subscribers catch up on output they missed. The plugin's own waiters subscribe the same
+//! way.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+use tauri::ipc::Channel;
+use tokio::sync::mpsc;
+
+use crate::models::{ProcessEvent, ReplayConfig};
+
+/// Replay buffer limits, see [`ReplayConfig`].
+const DEFAULT_REPLAY_LINES: usize = 1000;
+const DEFAULT_REPLAY_BYTES: usize = 1024 * 1024;
+
+struct Subscription {
+    id: u64,
+    sink: Sink,
+}
+
+/// Where a subscription's events go.
+#[derive(Clone)]
+enum Sink {
+    Channel {
+        /// Webview the channel belongs to, so its subscriptions can be dropped with it
+        webview: String,
+        /// Window of that webview. Once a window is destroyed its webviews can no longer be
+        /// listed, so its subscriptions are found by this label instead.
+        window: String,
+        channel: Channel<ProcessEvent>,
+    },
+    /// A waiter inside the plugin, such as `wait_for_output`
+    Listener(mpsc::UnboundedSender<ProcessEvent>),
+}
+
+impl Sink {
+    /// Returns whether the receiving end is still there.
+    fn send(&self, event: ProcessEvent) -> bool {
+        match self {
+            Self::Channel { channel, .. } => channel.send(event).is_ok(),
+            Self::Listener(tx) => tx.send(event).is_ok(),
+        }
+    }
+}
+
+/// Recent output of a process, oldest first.
+struct Replay {
+    /// Sequence number of the next line read; keeps counting across instances
+    next_seq: u64,
+    events: VecDeque<ProcessEvent>,
+    /// Lines in `events`, counting each line of a batch
+    lines: usize,
+    bytes: usize,
+    max_lines: usize,
+    max_bytes: usize,
+}
+
+impl Default for Replay {
+    fn default() -> Self {
+        Self {
+            next_seq: 1,
+            events: VecDeque::new(),
+            lines: 0,
+            bytes: 0,
+            max_lines: DEFAULT_REPLAY_LINES,
+            max_bytes: DEFAULT_REPLAY_BYTES,
+        }
+    }
+}
+
+impl Replay {
+    fn push(&mut self, event: ProcessEvent) {
+        self.lines += event.line_count();
+        self.bytes += event.data_len();
+        self.events.push_back(event);
+        while self.lines > self.max_lines || self.bytes > self.max_bytes {
+            match self.events.pop_front() {
+                Some(event) => {
+                    self.lines -= event.line_count();
+                    self.bytes -= event.data_len();
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+/// Subscribers and replay buffer of one process name. Subscriptions outlive instances, so
+/// restarts keep streaming.
+#[derive(Default)]
+struct Topic {
+    // Never held across an await, so output readers can publish without going async, nor
+    // while sending, so numbering a line or subscribing never waits on a slow channel
+    state: Mutex<TopicState>,
+    /// Ticket of the next delivery to go out. A delivery takes its ticket under `state`, along
+    /// with the subscribers it sends to, then waits its turn, so every subscriber sees the
+    /// events of the name in the order they were buffered.
+    turn: Mutex<u64>,
+    turn_taken: Condvar,
+}
+
+/// Subscribers and the replay buffer share a lock, so a subscriber replaying from the
+/// buffer can neither miss nor see twice an event published concurrently.
+#[derive(Default)]
+struct TopicState {
+    subscriptions: Vec<Subscription>,
+    replay: Replay,
+    next_ticket: u64,
+}
+
+impl TopicState {
+    fn take_ticket(&mut self) -> u64 {
+        let ticket = self.next_ticket;
+        self.next_ticket += 1;
+        ticket
+    }
+}
+
+impl Topic {
+    fn lock(&self) -> MutexGuard<'_, TopicState> {
+        lock(&self.state)
+    }
+
+    /// Sends `events` to each of `sinks` once every delivery with an earlier ticket has gone
+    /// out, then drops the subscriptions whose receiving end is gone.
+    fn deliver(&self, ticket: u64, sinks: Vec<(u64, Sink)>, events: &[ProcessEvent]) {
+        let mut turn = lock(&self.turn);
+        while *turn != ticket {
+            turn = self
+                .turn_taken
+                .wait(turn)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        let gone: Vec<u64> = sinks
+            .into_iter()
+            .filter(|(_, sink)| !events.iter().all(|event| sink.send(event.clone())))
+            .map(|(id, _)| id)
+            .collect();
+        *turn += 1;
+        drop(turn);
+        self.turn_taken.notify_all();
+        if !gone.is_empty() {
+            self.lock().subscriptions.retain(|s| !gone.contains(&s.id));
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Subscriptions {
+    /// Only held to look a name up, never while a topic is locked
+    topics: Mutex<HashMap<String, Arc<Topic>>>,
+    next_id: AtomicU64,
+}
+
+impl Subscriptions {
+    /// Subscribes `channel` to the events of `name`. With `from_seq`, buffered output with
+    /// that sequence number or later is sent first, including a batch whose later lines
+    /// qualify; 0 replays the whole buffer.
+    pub(crate) fn add(
+        &self,
+        name: String,
+        webview: String,
+        window: String,
+        channel: Channel<ProcessEvent>,
+        from_seq: Option<u64>,
+    ) -> u64 {
+        let sink = Sink::Channel {
+            webview,
+            window,
+            channel,
+        };
+        self.insert(&name, sink, from_seq)
+    }
+
+    /// Subscribes a waiter inside the plugin to the events of `name`, starting with the whole
+    /// replay buffer. The subscription goes away with the receiver, or with [`Self::remove`].
+    pub(crate) fn listen(&self, name: String) -> (u64, mpsc::UnboundedReceiver<ProcessEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (self.insert(&name, Sink::Listener(tx), Some(0)), rx)
+    }
+
+    fn insert(&self, name: &str, sink: Sink, from_seq: Option<u64>) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let topic = self.topic(name);
+        let mut state = topic.lock();
+        let replayed: Vec<ProcessEvent> = match from_seq {
+            Some(from_seq) => state
+                .replay
+                .events
+                .iter()
+                .filter(|event| event.last_seq().is_some_and(|seq| seq >= from_seq))
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+        state.subscriptions.push(Subscription {
+            id,
+            sink: sink.clone(),
+        });
+        if !replayed.is_empty() {
+            // Ahead of anything published from now on
+            let ticket = state.take_ticket();
+            drop(state);
+            topic.deliver(ticket, vec![(id, sink)], &replayed);
+        }
+        id
+    }
+
+    pub(crate) fn remove(&self, id: u64) {
+        for topic in self.topics() {
+            topic.lock().subscriptions.retain(|s| s.id != id);
+        }
+    }
+
+    /// Drops every subscription made by `webview`, e.g. once it navigates away or is closed.
+    pub(crate) fn remove_webview(&self, webview: &str) {
+        for topic in self.topics() {
+            topic
+                .lock()
+                .subscriptions
+                .retain(|s| !matches!(&s.sink, Sink::Channel { webview: w, .. } if w == webview));
+        }
+    }
+
+    /// Drops every subscription made by a webview of `window`, once the window is destroyed.
+    pub(crate) fn remove_window(&self, window: &str) {
+        for topic in self.topics() {
+            topic
+                .lock()
+                .subscriptions
+                .retain(|s| !matches!(&s.sink, Sink::Channel { window: w, .. } if w == window));
+        }
+    }
+
+    /// Empties the replay buffer of `name` for a new instance and applies its limits.
+    /// Sequence numbers carry on from the previous instance.
+    pub(crate) fn reset_replay(&self, name: &str, config: Option<&ReplayConfig>) {
+        let topic = self.topic(name);
+        let replay = &mut topic.lock().replay;
+        replay.events.clear();
+        replay.lines = 0;
+        replay.bytes = 0;
+        replay.max_lines = config
+            .and_then(|c| c.max_lines)
+            .unwrap_or(DEFAULT_REPLAY_LINES);
+        replay.max_bytes = config
+            .and_then(|c| c.max_bytes)
+            .unwrap_or(DEFAULT_REPLAY_BYTES);
+    }
+
+    /// Drops the buffered output of a process that is no longer tracked. Its sequence numbers
+    /// carry on if the name is spawned again, so they never repeat for the life of the app.
+    pub(crate) fn remove_replay(&self, name: &str) {
+        if let Some(topic) = lock(&self.topics).get(name) {
+            let replay = &mut topic.lock().replay;
+            replay.events = VecDeque::new();
+            replay.lines = 0;
+            replay.bytes = 0;
+        }
+    }
+
+    /// Takes the next sequence number of `name`, for a line that was just read.
+    pub(crate) fn next_seq(&self, name: &str) -> u64 {
+        let topic = self.topic(name);
+        let replay = &mut topic.lock().replay;
+        let seq = replay.next_seq;
+        replay.next_seq += 1;
+        seq
+    }
+
+    /// Buffers output events, then sends the event to the subscribers of its process,
+    /// dropping those whose channel is gone.
+    pub(crate) fn publish(&self, event: &ProcessEvent) {
+        let topic = self.topic(event.name());
+        let mut state = topic.lock();
+        // Output is buffered, lifecycle events are not
+        if event.seq().is_some() {
+            state.replay.push(event.clone());
+        }
+        if state.subscriptions.is_empty() {
+            return;
+        }
+        let sinks = state
+            .subscriptions
+            .iter()
+            .map(|s| (s.id, s.sink.clone()))
+            .collect();
+        let ticket = state.take_ticket();
+        drop(state);
+        topic.deliver(ticket, sinks, std::slice::from_ref(event));
+    }
+
+    fn topic(&self, name: &str) -> Arc<Topic> {
+        let mut topics = lock(&self.topics);
+        match topics.get(name) {
+            Some(topic) => topic.clone(),
+            None => topics.entry(name.to_string()).or_default().clone(),
+        }
+    }
+
+    fn topics(&self) -> Vec<Arc<Topic>> {
+        lock(&self.topics).values().cloned().collect()
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // A panic while holding a lock cannot leave the lists inconsistent
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use tauri::ipc::InvokeResponseBody;
+
+    use super::*;
+    use crate::models::{ExitEventPayload, ExitReason, StdioEventPayload, StdioStream};
+
+    type Received = Arc<Mutex<Vec<serde_json::Value>>>;
+
+    fn recorder() -> (Channel<ProcessEvent>, Received) {
+        let received = Received::default();
+        let sink = received.clone();
+        let channel = Channel::new(move |body| {
+            if let InvokeResponseBody::Json(json) = body {
+                sink.lock()
+                    .unwrap()
+                    .push(serde_json::from_str(&json).unwrap());
+            }
+            Ok(())
+        });
+        (channel, received)
+    }
+
+    fn stdout(seq: u64, data: &str) -> ProcessEvent {
+        ProcessEvent::Stdout(StdioEventPayload {
+            name: "p".to_string(),
+            instance_id: 1,
+            seq,
+            stream: StdioStream::Stdout,
+            captured_at: 0,
+            data: data.to_string(),
+        })
+    }
+
+    fn exit() -> ProcessEvent {
+        ProcessEvent::Exit(ExitEventPayload {
+            name: "p".to_string(),
+            instance_id: 1,
+            code: Some(0),
+            signal: None,
+            reason: ExitReason::Exited,
+            uptime_ms: 0,
+        })
+    }
+
+    /// Numbers and publishes a line of output of `p`.
+    fn publish_line(subscriptions: &Subscriptions, data: &str) {
+        let seq = subscriptions.next_seq("p");
+        subscriptions.publish(&stdout(seq, data));
+    }
+
+    /// `(event, seq, data)` of each message a channel received.
+    fn received(received: &Received) -> Vec<(String, Option<u64>, Option<String>)> {
+        received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|message| {
+                (
+                    message["event"].as_str().unwrap().to_string(),
+                    message["data"]["seq"].as_u64(),
+                    message["data"]["data"].as_str().map(str::to_string),
+                )
+            })
+            .collect()
+    }
+
+    fn data(rx: &Received) -> Vec<String> {
+        received(rx)
+            .into_iter()
+            .filter_map(|(_, _, data)| data)
+            .collect()
+    }
+
+    #[test]
+    fn next_seq_numbers_output_across_instances() {
+        let subscriptions = Subscriptions::default();
+        assert_eq!(subscriptions.next_seq("p"), 1);
+        assert_eq!(subscriptions.next_seq("p"), 2);
+        assert_eq!(subscriptions.next_seq("q"), 1);
+        subscriptions.reset_replay("p", None);
+        assert_eq!(subscriptions.next_seq("p"), 3);
+        subscriptions.remove_replay("p");
+        assert_eq!(subscriptions.next_seq("p"), 4);
+    }
+
+    #[test]
+    fn replay_keeps_the_most_recent_lines() {
+        let subscriptions = Subscriptions::default();
+        let config = ReplayConfig {
+            max_lines: Some(2),
+            max_bytes: None,
+        };
+        subscriptions.reset_replay("p", Some(&config));
+        for line in ["a", "b", "c"] {
+            publish_line(&subscriptions, line);
+        }
+        let (channel, rx) = recorder();
+        subscriptions.add("p".into(), "main".into(), "main".into(), channel, Some(0));
+        assert_eq!(data(&rx), ["b", "c"]);
+    }
+
+    #[test]
+    fn replay_keeps_at_most_max_bytes() {
+        let subscriptions = Subscriptions::default();
+        let config = ReplayConfig {
+            max_lines: None,
+            max_bytes: Some(5),
+        };
+        subscriptions.reset_replay("p", Some(&config));
+        for line in ["aa", "bb", "cc"] {
+            publish_line(&subscriptions, line);
+        }
+        let (channel, rx) = recorder();
+        subscriptions.add("p".into(), "main".into(), "main".into(), channel, Some(0));
+        assert_eq!(data(&rx), ["bb", "cc"]);
+    }
+
+    #[test]
+    fn replay_starts_at_from_seq() {
+        let subscriptions = Subscriptions::default();
+        for line in ["a", "b", "c"] {
+            publish_line(&subscriptions, line);
+        }
+        let (channel, rx) = recorder();
+        subscriptions.add("p".into(), "main".into(), "main".into(), channel, Some(2));
+        assert_eq!(data(&rx), ["b", "c"]);
+
+        let (channel, rx) = recorder();
+        subscriptions.add("p".into(), "main".into(), "main".into(), channel, None);
+        assert!(data(&rx).is_empty());
+    }
+
+    #[test]
+    fn replay_hands_over_to_live_events() {
+        let subscriptions = Subscriptions::default();
+        publish_line(&subscriptions, "a");
+        let (channel, rx) = recorder();
+        subscriptions.add("p".into(), "main".into(), "main".into(), channel, Some(0));
+        publish_line(&subscriptions, "b");
+        subscriptions.publish(&exit());
+        assert_eq!(
+            received(&rx),
+            [
+                ("stdout".to_string(), Some(1), Some("a".to_string())),
+                ("stdout".to_string(), Some(2), Some("b".to_string())),
+                ("exit".to_string(), None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn reset_replay_empties_the_buffer() {
+        let subscriptions = Subscriptions::default();
+        publish_line(&subscriptions, "a");
+        subscriptions.reset_replay("p", None);
+        publish_line(&subscriptions, "b");
+        let (channel, rx) = recorder();
+        subscriptions.add("p".into(), "main".into(), "main".into(), channel, Some(0));
+        assert_eq!(data(&rx), ["b"]);
+    }
+
+    #[test]
+    fn remove_replay_empties_the_buffer() {
+        let subscriptions = Subscriptions::default();
+        publish_line(&subscriptions, "a");
+        subscriptions.remove_replay("p");
+        let (channel, rx) = recorder();
+        subscriptions.add("p".into(), "main".into(), "main".into(), channel, Some(0));
+        assert!(data(&rx).is_empty());
+    }
+
+    #[test]
+    fn subscriptions_end_with_their_webview() {
+        let subscriptions = Subscriptions::default();
+        let (channel, kept) = recorder();
+        subscriptions.add("p".into(), "main".into(), "main".into(), channel, None);
+        let (channel, dropped) = recorder();
+        subscriptions.add("p".into(), "other".into(), "main".into(), channel, None);
+        let (channel, removed) = recorder();
+        let id = subscriptions.add("p".into(), "main".into(), "main".into(), channel, None);
+        subscriptions.remove_webview("other");
+        subscriptions.remove(id);
+        publish_line(&subscriptions, "a");
+        assert_eq!(data(&kept), ["a"]);
+        assert!(data(&dropped).is_empty());
+        assert!(data(&removed).is_empty());
+    }
+
+    #[test]
+    fn concurrent_publishers_deliver_in_buffer_order() {
+        let subscriptions = Arc::new(Subscriptions::default());
+        let (channel, first) = recorder();
+        subscriptions.add("p".into(), "main".into(), "main".into(), channel, None);
+        let (channel, second) = recorder();
+        subscriptions.add("p".into(), "main".into(), "main".into(), channel, None);
+        let publishers: Vec<_> = (0..4)
+            .map(|_| {
+                let subscriptions = subscriptions.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        publish_line(&subscriptions, "x");
+                    }
+                })
+            })
+            .collect();
+        for publisher in publishers {
+            publisher.join().unwrap();
+        }
+
+        let (channel, replayed) = recorder();
+        subscriptions.add("p".into(), "main".into(), "main".into(), channel, Some(0));
+        let seqs = |rx: &Received| -> Vec<Option<u64>> {
+            received(rx).into_iter().map(|(_, seq, _)| seq).collect()
+        };
+        assert_eq!(seqs(&first).len(), 400);
+        assert_eq!(seqs(&first), seqs(&second));
+        assert_eq!(seqs(&first), seqs(&replayed));
+    }
+
+    #[test]
+    fn closed_channels_are_dropped() {
+        let subscriptions = Subscriptions::default();
+        let sent = Arc::new(AtomicU64::new(0));
+        let counter = sent.clone();
+        let channel = Channel::new(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            Err(tauri::Error::WebviewNotFound)
+        });
+        subscriptions.add("p".into(), "main".into(), "main".into(), channel, None);
+        publish_line(&subscriptions, "a");
+        publish_line(&subscriptions, "b");
+        assert_eq!(sent.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn subscriptions_end_with_their_window() {
+        let subscriptions = Subscriptions::default();
+        let (channel, kept) = recorder();
+        subscriptions.add("p".into(), "main".into(), "main".into(), channel, None);
+        let (channel, dropped) = recorder();
+        subscriptions.add("p".into(), "panel".into(), "tools".into(), channel, None);
+        subscriptions.remove_window("tools");
+        publish_line(&subscriptions, "a");
+        assert_eq!(data(&kept), ["a"]);
+        assert!(data(&dropped).is_empty());
+    }
+}
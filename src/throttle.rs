@@ -0,0 +1,388 @@
+//! Per-process rate limit on stdout and stderr, so a worker flooding its output cannot
+//! freeze the webview with events.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use tokio::fs::{self, File};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::Mutex;
+
+use crate::models::{OutputStats, OverflowPolicy, RateLimitConfig, StdioStream};
+
+/// Period over which the limits are counted.
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Default for `RateLimitConfig::sample_every`.
+const DEFAULT_SAMPLE_EVERY: u64 = 100;
+
+/// Default for `RateLimitConfig::max_spill_bytes`.
+const DEFAULT_MAX_SPILL_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Shared by the stdout and stderr readers of one instance, so the cap covers both.
+pub(crate) struct OutputLimiter {
+    max_lines: Option<u64>,
+    max_bytes: Option<u64>,
+    policy: OverflowPolicy,
+    sample_every: u64,
+    max_spill_bytes: u64,
+    spill_path: PathBuf,
+    /// Held across spill-file writes, so only the output readers lock it
+    window: Mutex<Window>,
+    /// Kept apart from `window`, so status queries never wait on the spill file
+    stats: std::sync::Mutex<OutputStats>,
+}
+
+struct Window {
+    start: Instant,
+    /// Lines and bytes seen in the current window, forwarded or not
+    lines: u64,
+    bytes: u64,
+    /// Lines over the limit in the current window, for sampling
+    over_limit: u64,
+    /// Whether output was held back since stats were last reported
+    unreported: bool,
+    last_report: Option<Instant>,
+    /// Opened when the first line is spilled
+    spill: Option<BufWriter<File>>,
+    /// Bytes written to the current spill file
+    spill_len: u64,
+}
+
+impl OutputLimiter {
+    pub(crate) fn new(config: &RateLimitConfig, spill_path: PathBuf) -> Self {
+        Self {
+            max_lines: config.max_lines_per_sec,
+            max_bytes: config.max_bytes_per_sec,
+            policy: config.policy.unwrap_or_default(),
+            sample_every: config.sample_every.unwrap_or(DEFAULT_SAMPLE_EVERY),
+            max_spill_bytes: config.max_spill_bytes.unwrap_or(DEFAULT_MAX_SPILL_BYTES),
+            spill_path,
+            window: Mutex::new(Window {
+                start: Instant::now(),
+                lines: 0,
+                bytes: 0,
+                over_limit: 0,
+                unreported: false,
+                last_report: None,
+                spill: None,
+                spill_len: 0,
+            }),
+            stats: std::sync::Mutex::new(OutputStats::default()),
+        }
+    }
+
+    pub(crate) fn policy(&self) -> OverflowPolicy {
+        self.policy
+    }
+
+    /// Totals since the instance started.
+    pub(crate) fn stats(&self) -> OutputStats {
+        self.stats_mut().clone()
+    }
+
+    fn stats_mut(&self) -> std::sync::MutexGuard<'_, OutputStats> {
+        // Plain counters, which a panic cannot leave inconsistent
+        self.stats.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Counts a line against the current window and returns whether it should be forwarded.
+    /// Lines over the limit are dropped, sampled or spilled according to the policy; lines
+    /// that cannot be spilled are dropped.
+    pub(crate) async fn admit(&self, stream: StdioStream, captured_at: u64, data: &str) -> bool {
+        let mut window = self.window.lock().await;
+        if window.start.elapsed() >= WINDOW {
+            window.start = Instant::now();
+            window.lines = 0;
+            window.bytes = 0;
+            window.over_limit = 0;
+            self.flush_spill(&mut window).await;
+        }
+        window.lines += 1;
+        window.bytes += data.len() as u64;
+        let over = self.max_lines.is_some_and(|max| window.lines > max)
+            || self.max_bytes.is_some_and(|max| window.bytes > max);
+        if !over {
+            return true;
+        }
+
+        window.unreported = true;
+        window.over_limit += 1;
+        match self.policy {
+            OverflowPolicy::Drop => {}
+            OverflowPolicy::Sample => {
+                if (window.over_limit - 1) % self.sample_every == 0 {
+                    return true;
+                }
+            }
+            OverflowPolicy::Spill => match self.spill(&mut window, stream, captured_at, data).await
+            {
+                Ok(()) => {
+                    let mut stats = self.stats_mut();
+                    stats.spilled_lines += 1;
+                    stats.spilled_bytes += data.len() as u64;
+                    return false;
+                }
+                Err(e) => {
+                    log::warn!(
+                        "could not spill output to {}: {}",
+                        self.spill_path.display(),
+                        e
+                    );
+                }
+            },
+        }
+        let mut stats = self.stats_mut();
+        stats.dropped_lines += 1;
+        stats.dropped_bytes += data.len() as u64;
+        false
+    }
+
+    /// Returns the stats to report if output was held back and the last report is at least
+    /// a window old.
+    pub(crate) async fn report_due(&self) -> Option<OutputStats> {
+        let mut window = self.window.lock().await;
+        if !window.unreported || window.last_report.is_some_and(|at| at.elapsed() < WINDOW) {
+            return None;
+        }
+        self.flush_spill(&mut window).await;
+        self.take_report(&mut window)
+    }
+
+    /// Flushes the spill file and returns the stats to report if output was held back since
+    /// the last report. Called when a stream ends.
+    pub(crate) async fn finish(&self) -> Option<OutputStats> {
+        let mut window = self.window.lock().await;
+        self.flush_spill(&mut window).await;
+        self.take_report(&mut window)
+    }
+
+    fn take_report(&self, window: &mut Window) -> Option<OutputStats> {
+        if !window.unreported {
+            return None;
+        }
+        window.unreported = false;
+        window.last_report = Some(Instant::now());
+        Some(self.stats())
+    }
+
+    async fn spill(
+        &self,
+        window: &mut Window,
+        stream: StdioStream,
+        captured_at: u64,
+        data: &str,
+    ) -> std::io::Result<()> {
+        let stream = match stream {
+            StdioStream::Stdout => "stdout",
+            StdioStream::Stderr => "stderr",
+            StdioStream::Ipc => "ipc",
+        };
+        let line = format!("{} {} {}\n", captured_at, stream, data);
+        if window.spill_len > 0 && window.spill_len + line.len() as u64 > self.max_spill_bytes {
+            if let Some(mut spill) = window.spill.take() {
+                spill.flush().await?;
+            }
+            fs::rename(&self.spill_path, self.rotated_spill_path()).await?;
+            window.spill_len = 0;
+        }
+        let spill = match &mut window.spill {
+            Some(spill) => spill,
+            None => {
+                if let Some(dir) = self.spill_path.parent() {
+                    fs::create_dir_all(dir).await?;
+                }
+                let file = File::create(&self.spill_path).await?;
+                self.stats_mut().spill_path = Some(self.spill_path.to_string_lossy().into_owned());
+                window.spill.insert(BufWriter::new(file))
+            }
+        };
+        spill.write_all(line.as_bytes()).await?;
+        window.spill_len += line.len() as u64;
+        Ok(())
+    }
+
+    /// Where the spill file goes once it is full.
+    fn rotated_spill_path(&self) -> PathBuf {
+        let mut path = self.spill_path.clone().into_os_string();
+        path.push(".1");
+        path.into()
+    }
+
+    async fn flush_spill(&self, window: &mut Window) {
+        if let Some(spill) = &mut window.spill {
+            if let Err(e) = spill.flush().await {
+                log::warn!("could not flush {}: {}", self.spill_path.display(), e);
+            }
+        }
+    }
+}
+
+/// The spill files are only reachable through the instance's stats, so they go with it.
+impl Drop for OutputLimiter {
+    fn drop(&mut self) {
+        if self
+            .stats
+            .get_mut()
+            .map_or(true, |s| s.spill_path.is_some())
+        {
+            let _ = std::fs::remove_file(&self.spill_path);
+            let _ = std::fs::remove_file(self.rotated_spill_path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(config: RateLimitConfig) -> OutputLimiter {
+        OutputLimiter::new(&config, std::env::temp_dir().join("unused.log"))
+    }
+
+    async fn admit_all(limiter: &OutputLimiter, lines: &[&str]) -> Vec<bool> {
+        let mut admitted = Vec::new();
+        for line in lines {
+            admitted.push(limiter.admit(StdioStream::Stdout, 0, line).await);
+        }
+        admitted
+    }
+
+    #[tokio::test]
+    async fn drops_lines_over_the_limit() {
+        let limiter = limiter(RateLimitConfig {
+            max_lines_per_sec: Some(3),
+            ..Default::default()
+        });
+        let admitted = admit_all(&limiter, &["a", "b", "c", "dd", "eee"]).await;
+        assert_eq!(admitted, [true, true, true, false, false]);
+        let stats = limiter.stats();
+        assert_eq!(stats.dropped_lines, 2);
+        assert_eq!(stats.dropped_bytes, 5);
+    }
+
+    #[tokio::test]
+    async fn counts_bytes_against_the_limit() {
+        let limiter = limiter(RateLimitConfig {
+            max_bytes_per_sec: Some(5),
+            ..Default::default()
+        });
+        let admitted = admit_all(&limiter, &["abc", "de", "f"]).await;
+        assert_eq!(admitted, [true, true, false]);
+    }
+
+    #[tokio::test]
+    async fn samples_one_in_sample_every() {
+        let limiter = limiter(RateLimitConfig {
+            max_lines_per_sec: Some(1),
+            policy: Some(OverflowPolicy::Sample),
+            sample_every: Some(3),
+            ..Default::default()
+        });
+        let admitted = admit_all(&limiter, &["1", "2", "3", "4", "5", "6", "7", "8"]).await;
+        // The first line is under the limit, then every third line over it goes through
+        assert_eq!(
+            admitted,
+            [true, true, false, false, true, false, false, true]
+        );
+        assert_eq!(limiter.stats().dropped_lines, 4);
+    }
+
+    #[tokio::test]
+    async fn limit_resets_with_each_window() {
+        let limiter = limiter(RateLimitConfig {
+            max_lines_per_sec: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(admit_all(&limiter, &["a", "b"]).await, [true, false]);
+        limiter.window.lock().await.start -= WINDOW;
+        assert_eq!(admit_all(&limiter, &["c", "d"]).await, [true, false]);
+        assert_eq!(limiter.stats().dropped_lines, 2);
+    }
+
+    #[tokio::test]
+    async fn reports_at_most_once_per_window() {
+        let limiter = limiter(RateLimitConfig {
+            max_lines_per_sec: Some(1),
+            ..Default::default()
+        });
+        admit_all(&limiter, &["a"]).await;
+        assert!(limiter.report_due().await.is_none());
+        admit_all(&limiter, &["b"]).await;
+        assert_eq!(limiter.report_due().await.unwrap().dropped_lines, 1);
+        admit_all(&limiter, &["c"]).await;
+        assert!(limiter.report_due().await.is_none());
+        // The end of the stream reports what is left
+        assert_eq!(limiter.finish().await.unwrap().dropped_lines, 2);
+        assert!(limiter.finish().await.is_none());
+    }
+
+    fn spiller(test: &str, max_spill_bytes: Option<u64>) -> (OutputLimiter, PathBuf) {
+        let dir = std::env::temp_dir().join(format!(
+            "tauri-plugin-js-spill-{}-{}",
+            test,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("spill.log");
+        let limiter = OutputLimiter::new(
+            &RateLimitConfig {
+                max_lines_per_sec: Some(1),
+                policy: Some(OverflowPolicy::Spill),
+                max_spill_bytes,
+                ..Default::default()
+            },
+            path.clone(),
+        );
+        (limiter, path)
+    }
+
+    #[tokio::test]
+    async fn spills_lines_over_the_limit() {
+        let (limiter, path) = spiller("lines", None);
+        assert!(limiter.admit(StdioStream::Stdout, 1, "a").await);
+        assert!(!limiter.admit(StdioStream::Stdout, 2, "b").await);
+        assert!(!limiter.admit(StdioStream::Stderr, 3, "c d").await);
+        let stats = limiter.finish().await.unwrap();
+        assert_eq!(stats.spilled_lines, 2);
+        assert_eq!(stats.spilled_bytes, 4);
+        assert_eq!(stats.dropped_lines, 0);
+        assert_eq!(stats.spill_path, Some(path.to_string_lossy().into_owned()));
+        let spilled = std::fs::read_to_string(&path).unwrap();
+        drop(limiter);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+        assert_eq!(spilled, "2 stdout b\n3 stderr c d\n");
+    }
+
+    #[tokio::test]
+    async fn spill_file_rotates_when_full() {
+        let (limiter, path) = spiller("rotate", Some(30));
+        for (at, line) in ["a", "bbbb", "cccc", "dddd"].into_iter().enumerate() {
+            limiter.admit(StdioStream::Stdout, at as u64, line).await;
+        }
+        limiter.finish().await;
+        let current = std::fs::read_to_string(&path).unwrap();
+        let rotated = std::fs::read_to_string(limiter.rotated_spill_path()).unwrap();
+        drop(limiter);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+        // Each spilled line is 14 bytes, so a file holds two
+        assert_eq!(rotated, "1 stdout bbbb\n2 stdout cccc\n");
+        assert_eq!(current, "3 stdout dddd\n");
+    }
+
+    #[tokio::test]
+    async fn spill_files_go_with_the_limiter() {
+        let (limiter, path) = spiller("drop", Some(20));
+        for line in ["a", "b", "c", "d"] {
+            limiter.admit(StdioStream::Stdout, 0, line).await;
+        }
+        limiter.finish().await;
+        let rotated = limiter.rotated_spill_path();
+        assert!(path.exists());
+        assert!(rotated.exists());
+        drop(limiter);
+        assert!(!path.exists());
+        assert!(!rotated.exists());
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
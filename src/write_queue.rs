@@ -0,0 +1,186 @@
+//! Bounded queues in front of a process's stdin, IPC pipe and socket connections. Each queue
+//! is drained by a task that owns the writer, so a child that stops reading only stalls its
+//! own writes, never the process map.
+
+use std::io;
+
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::models::{QueueFullPolicy, WriteQueueConfig};
+
+/// Default for `WriteQueueConfig::max_pending`.
+const DEFAULT_MAX_PENDING: usize = 64;
+
+struct PendingWrite {
+    frame: Vec<u8>,
+    done: oneshot::Sender<io::Result<()>>,
+}
+
+/// Handle to a writer task; the writer is closed once every handle is dropped and the
+/// queued writes have gone out.
+#[derive(Clone)]
+pub(crate) struct WriteQueue {
+    tx: mpsc::Sender<PendingWrite>,
+    when_full: QueueFullPolicy,
+}
+
+/// Resolves once a queued frame has been written and flushed.
+pub(crate) struct Written(oneshot::Receiver<io::Result<()>>);
+
+impl Written {
+    pub(crate) async fn wait(self) -> io::Result<()> {
+        self.0.await.unwrap_or_else(|_| Err(closed()))
+    }
+}
+
+impl WriteQueue {
+    /// Moves `writer` into a new task that writes queued frames in order.
+    pub(crate) fn spawn(
+        writer: impl AsyncWrite + Unpin + Send + 'static,
+        config: Option<&WriteQueueConfig>,
+    ) -> Self {
+        let max_pending = config
+            .and_then(|c| c.max_pending)
+            .unwrap_or(DEFAULT_MAX_PENDING);
+        let (tx, rx) = mpsc::channel(max_pending);
+        tauri::async_runtime::spawn(drain(writer, rx));
+        Self {
+            tx,
+            when_full: config.and_then(|c| c.when_full).unwrap_or_default(),
+        }
+    }
+
+    /// Queues `frame`. When the queue is full this waits for room or fails with
+    /// [`io::ErrorKind::WouldBlock`], according to the queue's policy.
+    pub(crate) async fn enqueue(&self, frame: Vec<u8>) -> io::Result<Written> {
+        let (done, written) = oneshot::channel();
+        let write = PendingWrite { frame, done };
+        match self.when_full {
+            QueueFullPolicy::Wait => self.tx.send(write).await.map_err(|_| closed())?,
+            QueueFullPolicy::Error => self.tx.try_send(write).map_err(|e| match e {
+                mpsc::error::TrySendError::Full(_) => {
+                    io::Error::new(io::ErrorKind::WouldBlock, "write queue is full")
+                }
+                mpsc::error::TrySendError::Closed(_) => closed(),
+            })?,
+        }
+        Ok(Written(written))
+    }
+
+    /// Queues `frame` and waits until it has been written.
+    pub(crate) async fn write(&self, frame: Vec<u8>) -> io::Result<()> {
+        self.enqueue(frame).await?.wait().await
+    }
+}
+
+async fn drain(mut writer: impl AsyncWrite + Unpin, mut rx: mpsc::Receiver<PendingWrite>) {
+    while let Some(PendingWrite { frame, done }) = rx.recv().await {
+        let result = async {
+            writer.write_all(&frame).await?;
+            writer.flush().await
+        }
+        .await;
+        let failed = result.is_err();
+        let _ = done.send(result);
+        // The pipe is broken; dropping the queue fails the writes still waiting in it
+        if failed {
+            break;
+        }
+    }
+}
+
+fn closed() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "writer is closed")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    fn config(max_pending: usize, when_full: QueueFullPolicy) -> WriteQueueConfig {
+        WriteQueueConfig {
+            max_pending: Some(max_pending),
+            when_full: Some(when_full),
+        }
+    }
+
+    /// Lets the drain task take the frames queued so far.
+    async fn settle() {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    #[tokio::test]
+    async fn writes_frames_in_order() {
+        let (writer, mut reader) = tokio::io::duplex(64);
+        let queue = WriteQueue::spawn(writer, None);
+        let mut written = Vec::new();
+        for frame in ["one\n", "two\n", "three\n"] {
+            written.push(queue.enqueue(frame.into()).await.unwrap());
+        }
+        for w in written {
+            w.wait().await.unwrap();
+        }
+        drop(queue);
+        let mut out = String::new();
+        reader.read_to_string(&mut out).await.unwrap();
+        assert_eq!(out, "one\ntwo\nthree\n");
+    }
+
+    #[tokio::test]
+    async fn full_queue_fails_with_would_block() {
+        let (writer, mut reader) = tokio::io::duplex(1);
+        let queue = WriteQueue::spawn(writer, Some(&config(1, QueueFullPolicy::Error)));
+        // The drain task is stuck on the first frame and the second fills the queue
+        let first = queue.enqueue(b"ab".to_vec()).await.unwrap();
+        settle().await;
+        let second = queue.enqueue(b"c".to_vec()).await.unwrap();
+        let err = queue.enqueue(b"d".to_vec()).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+        let mut out = [0; 3];
+        reader.read_exact(&mut out).await.unwrap();
+        assert_eq!(&out, b"abc");
+        first.wait().await.unwrap();
+        second.wait().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn full_queue_waits_for_the_reader() {
+        let (writer, mut reader) = tokio::io::duplex(1);
+        let queue = WriteQueue::spawn(writer, Some(&config(1, QueueFullPolicy::Wait)));
+        queue.enqueue(b"ab".to_vec()).await.unwrap();
+        settle().await;
+        queue.enqueue(b"c".to_vec()).await.unwrap();
+        let third = queue.enqueue(b"d".to_vec());
+        tokio::pin!(third);
+        assert!(tokio::time::timeout(Duration::from_millis(50), &mut third)
+            .await
+            .is_err());
+
+        let mut out = [0; 2];
+        reader.read_exact(&mut out).await.unwrap();
+        let third = third.await.unwrap();
+        let mut out = [0; 2];
+        reader.read_exact(&mut out).await.unwrap();
+        assert_eq!(&out, b"cd");
+        third.wait().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn closed_reader_fails_the_write() {
+        let (writer, reader) = tokio::io::duplex(64);
+        let queue = WriteQueue::spawn(writer, None);
+        drop(reader);
+        let err = queue.write(b"lost".to_vec()).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        // The drain task has gone, so later writes fail too
+        settle().await;
+        let err = queue.write(b"lost".to_vec()).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+}